@@ -79,33 +79,191 @@
 //! assert_eq!(result, ANSWER);
 //! ```
 
+pub mod inventory;
+
+use crate::parsing::{groups, read_groups, Group, ParseError};
+use inventory::Inventory;
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fmt::Display,
+    io::{self, BufRead},
+    num::ParseIntError,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidCalories {
+        /// 1-based line number in the inventory
+        line: usize,
+        content: String,
+        reason: ParseIntError,
+    },
+    /// the calories of one elf do not fit in a `u64` after adding `line`
+    CaloriesOverflow {
+        line: usize,
+    },
+    /// the calories of the `top` elfes do not fit in a `u64`
+    TotalOverflow,
+    Io(io::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidCalories {
+                line,
+                content,
+                reason,
+            } => write!(
+                f,
+                "line {line}: cannot read calories from {content:?}: {reason}"
+            ),
+            Error::CaloriesOverflow { line } => {
+                write!(f, "line {line}: calories carried by one elf overflow")
+            }
+            Error::TotalOverflow => write!(f, "calories carried by the top elfes overflow"),
+            Error::Io(error) => write!(f, "cannot read inventory: {error}"),
+        }
+    }
+}
+
+impl From<ParseError<ParseIntError>> for Error {
+    fn from(error: ParseError<ParseIntError>) -> Self {
+        Error::InvalidCalories {
+            line: error.line,
+            content: error.content,
+            reason: error.reason,
+        }
+    }
+}
+
+/// Calories of every item and their total for the elf whose inventory is `group`
+fn parse_elf(group: &Group) -> Result<(Vec<u64>, u64), Error> {
+    let items = group.parse_lines::<u64>()?;
+    let mut total: u64 = 0;
+    for (line, calories) in (group.line..).zip(&items) {
+        total = total
+            .checked_add(*calories)
+            .ok_or(Error::CaloriesOverflow { line })?;
+    }
+    Ok((items, total))
+}
+
+/// Splits `inventory_all` into the calories of every item each elf is carrying. Elfes separate
+/// their own inventory from each other with one or more blank lines and put every item on its own
+/// line. Both `\n` and `\r\n` line endings are accepted and blank lines before the first and after
+/// the last inventory are ignored.
+///
+/// # Errors
+/// [`Error::InvalidCalories`] for the first line that is not a number and
+/// [`Error::CaloriesOverflow`] when the calories of one elf do not fit in a `u64`
+pub fn parse_inventories(inventory_all: &str) -> Result<Vec<Vec<u64>>, Error> {
+    let inventory = Inventory::parse(inventory_all)?;
+    Ok(inventory.elves.into_iter().map(|elf| elf.items).collect())
+}
+
+/// Iterator over the calories carried by each elf. Stops after the first error.
+pub struct ElfTotals<I> {
+    totals: I,
+    failed: bool,
+}
+
+impl<I> Iterator for ElfTotals<I>
+where
+    I: Iterator<Item = Result<u64, Error>>,
+{
+    type Item = Result<u64, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let total = self.totals.next()?;
+        self.failed = total.is_err();
+        Some(total)
+    }
+}
+
+/// Calories carried by each elf in `inventory_all`. See [`parse_inventories`] for the format.
+#[must_use]
+pub fn elf_totals(inventory_all: &str) -> ElfTotals<impl Iterator<Item = Result<u64, Error>> + '_> {
+    ElfTotals {
+        totals: groups(inventory_all).map(|group| Ok(parse_elf(&group)?.1)),
+        failed: false,
+    }
+}
+
+/// Calories carried by each elf in an inventory that is read one elf at a time from `reader`.
+#[must_use]
+pub fn read_elf_totals<R: BufRead>(
+    reader: R,
+) -> ElfTotals<impl Iterator<Item = Result<u64, Error>>> {
+    ElfTotals {
+        totals: read_groups(reader)
+            .map(|group| Ok(parse_elf(&group.map_err(Error::Io)?.as_group())?.1)),
+        failed: false,
+    }
+}
+
+/// The `top` largest `totals` from largest to smallest. Keeps at most `top` totals in memory
+/// which makes it `O(n log top)`.
+///
+/// # Errors
+/// the first error in `totals`
+pub fn top_totals<I>(totals: I, top: usize) -> Result<Vec<u64>, Error>
+where
+    I: IntoIterator<Item = Result<u64, Error>>,
+{
+    let mut heap = BinaryHeap::new();
+    for total in totals {
+        heap.push(Reverse(total?));
+        if heap.len() > top {
+            heap.pop();
+        }
+    }
+    Ok(heap
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(total)| total)
+        .collect())
+}
+
+fn sum_totals(totals: &[u64]) -> Result<u64, Error> {
+    totals
+        .iter()
+        .try_fold(0_u64, |sum, total| sum.checked_add(*total))
+        .ok_or(Error::TotalOverflow)
+}
+
+/// Number of calories the `top` elfes in the inventory read from `reader` are carrying.
+///
+/// # Errors
+/// if `reader` fails, a line is not a number or a sum overflows
+pub fn read_calories_of_top_elfes<R: BufRead>(reader: R, top: usize) -> Result<u64, Error> {
+    sum_totals(&top_totals(read_elf_totals(reader), top)?)
+}
+
+/// Returns number of calories `top` elfes are carrying as string. See [`parse_inventories`] for
+/// the format of `inventory_all`.
+///
+/// # Panics
+/// if a line in `inventory_all` is not a number or the calories overflow a `u64`
 #[must_use]
-/// Returns number of calories `top` elfes are carrying as string. Elfes separate their own
-/// inventory in `inventory_all` from each other (if any) with double new line feed `\n\n`
-/// and they separate calories for each of their products by a single new line feed `\n`.
 pub fn elf_carrying_most_calories(inventory_all: &str, top: usize) -> u64 {
-    let mut calories_carried_per_elfs = inventory_all
-        .split("\n\n")
-        .map(|inventory_one| -> u64 {
-            let calories: Vec<u64> = inventory_one
-                .lines()
-                .map(str::trim)
-                .skip_while(|&line| line.is_empty())
-                .map(|line| -> u64 { line.parse::<u64>().unwrap_or(0) })
-                .collect();
-            calories.iter().sum::<u64>()
-        })
-        .collect::<Vec<u64>>();
-
-    calories_carried_per_elfs.sort_unstable();
-    calories_carried_per_elfs.reverse();
-
-    calories_carried_per_elfs.iter().take(top).sum::<u64>()
+    let total = top_totals(elf_totals(inventory_all), top).and_then(|totals| sum_totals(&totals));
+    match total {
+        Ok(total) => total,
+        Err(error) => panic!("{error}"),
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::day_1::elf_carrying_most_calories;
+    use crate::day_1::{
+        elf_carrying_most_calories, elf_totals, parse_inventories, read_calories_of_top_elfes,
+        top_totals, Error,
+    };
 
     const INPUT: &str = include_str!("../puzzle_input/day_1.txt");
     const EXAMPLE_INPUT: &str = r"
@@ -141,4 +299,73 @@ mod tests {
             assert_eq!(result, ANSWER[index]);
         }
     }
+
+    #[test]
+    fn inventories() {
+        let inventories = parse_inventories(EXAMPLE_INPUT).unwrap();
+        assert_eq!(inventories.len(), 5);
+        assert_eq!(inventories[0], vec![1000, 2000, 3000]);
+        assert_eq!(inventories[4], vec![10000]);
+
+        let windows = EXAMPLE_INPUT.replace('\n', "\r\n") + "\r\n\r\n";
+        assert_eq!(parse_inventories(&windows).unwrap(), inventories);
+        assert_eq!(elf_carrying_most_calories(&windows, 3), EXAMPLE_ANSWER[1]);
+
+        let spaced = EXAMPLE_INPUT.replace("\n\n", "\n\n\n");
+        assert_eq!(parse_inventories(&spaced).unwrap(), inventories);
+        assert_eq!(parse_inventories("\n\n").unwrap(), Vec::<Vec<u64>>::new());
+    }
+
+    #[test]
+    fn invalid_calories() {
+        let error = parse_inventories("1000\n2000\n\n3O00\n").unwrap_err();
+        assert!(matches!(
+            &error,
+            Error::InvalidCalories { line: 4, content, .. } if content == "3O00"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "line 4: cannot read calories from \"3O00\": invalid digit found in string"
+        );
+        assert!(parse_inventories("-5").is_err());
+    }
+
+    #[test]
+    fn top() {
+        let totals = top_totals(elf_totals(EXAMPLE_INPUT), 3).unwrap();
+        assert_eq!(totals, vec![24000, 11000, 10000]);
+        assert_eq!(top_totals(elf_totals(EXAMPLE_INPUT), 0).unwrap(), vec![]);
+        assert_eq!(top_totals(elf_totals(EXAMPLE_INPUT), 10).unwrap().len(), 5);
+        assert_eq!(
+            top_totals(elf_totals(EXAMPLE_INPUT), usize::MAX).unwrap(),
+            vec![24000, 11000, 10000, 6000, 4000]
+        );
+
+        for (index, top) in [1, 3].iter().enumerate() {
+            let result = read_calories_of_top_elfes(INPUT.as_bytes(), *top).unwrap();
+            assert_eq!(result, ANSWER[index]);
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        let max = u64::MAX;
+        let error = read_calories_of_top_elfes(format!("1\n\n{max}\n1").as_bytes(), 1);
+        assert!(matches!(error, Err(Error::CaloriesOverflow { line: 4 })));
+
+        let error = read_calories_of_top_elfes(format!("{max}\n\n1").as_bytes(), 2);
+        assert!(matches!(error, Err(Error::TotalOverflow)));
+        assert_eq!(
+            read_calories_of_top_elfes(format!("{max}\n\n1").as_bytes(), 1).unwrap(),
+            max
+        );
+
+        let mut totals = elf_totals("1\n\nx\n\n2");
+        assert_eq!(totals.next().unwrap().unwrap(), 1);
+        assert!(matches!(
+            totals.next(),
+            Some(Err(Error::InvalidCalories { line: 3, .. }))
+        ));
+        assert!(totals.next().is_none());
+    }
 }
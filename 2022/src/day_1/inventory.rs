@@ -0,0 +1,333 @@
+//! Which elf carries what.
+//!
+//! [`Inventory`] keeps every item of every elf so the leaderboard can name the elves instead of
+//! only summing their calories. Elves are numbered from 1 in the order they appear in the input.
+
+use std::fmt::{Display, Write};
+
+use super::{parse_elf, Error};
+use crate::{output::Format, parsing::groups};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Elf {
+    /// 1-based position in the inventory
+    pub number: usize,
+    /// 1-based line of the first item
+    pub line: usize,
+    pub items: Vec<u64>,
+    pub total: u64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Inventory {
+    pub elves: Vec<Elf>,
+}
+
+impl Inventory {
+    /// See [`parse_inventories`](super::parse_inventories) for the format of `inventory_all`.
+    ///
+    /// # Errors
+    /// if a line is not a number or the calories of one elf overflow a `u64`
+    pub fn parse(inventory_all: &str) -> Result<Self, Error> {
+        let mut elves = vec![];
+
+        for group in groups(inventory_all) {
+            let (items, total) = parse_elf(&group)?;
+            elves.push(Elf {
+                number: elves.len() + 1,
+                line: group.line,
+                items,
+                total,
+            });
+        }
+
+        Ok(Self { elves })
+    }
+
+    /// Elves from the most to the least calories. Elves carrying the same amount share a rank and
+    /// are listed by number, the next rank skips the shared places (`1, 2, 2, 4`).
+    #[must_use]
+    pub fn leaderboard(&self) -> Vec<Entry> {
+        let mut elves = self.elves.iter().collect::<Vec<&Elf>>();
+        elves.sort_by_key(|elf| (std::cmp::Reverse(elf.total), elf.number));
+
+        let mut entries: Vec<Entry> = vec![];
+        for (position, elf) in elves.iter().enumerate() {
+            let rank = match entries.last_mut() {
+                Some(previous) if previous.total == elf.total => {
+                    previous.tied = true;
+                    previous.rank
+                }
+                _ => position + 1,
+            };
+            entries.push(Entry {
+                rank,
+                elf: elf.number,
+                total: elf.total,
+                items: elf.items.len(),
+                tied: entries
+                    .last()
+                    .is_some_and(|previous| previous.total == elf.total),
+            });
+        }
+        entries
+    }
+
+    fn sorted_totals(&self) -> Vec<u64> {
+        let mut totals = self.elves.iter().map(|elf| elf.total).collect::<Vec<u64>>();
+        totals.sort_unstable();
+        totals
+    }
+
+    /// Value below which `percent` percent of the totals fall, interpolated linearly between the
+    /// closest ranks. `None` without elves.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn percentile(&self, percent: f64) -> Option<f64> {
+        let totals = self.sorted_totals();
+        if totals.is_empty() {
+            return None;
+        }
+        let position = percent.clamp(0.0, 100.0) / 100.0 * (totals.len() - 1) as f64;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let lower = position.floor() as usize;
+        let upper = (lower + 1).min(totals.len() - 1);
+        let fraction = position - position.floor();
+        Some(totals[lower] as f64 + (totals[upper] as f64 - totals[lower] as f64) * fraction)
+    }
+
+    #[must_use]
+    pub fn median(&self) -> Option<f64> {
+        self.percentile(50.0)
+    }
+
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn mean(&self) -> Option<f64> {
+        if self.elves.is_empty() {
+            return None;
+        }
+        let sum = self.elves.iter().map(|elf| elf.total as f64).sum::<f64>();
+        Some(sum / self.elves.len() as f64)
+    }
+
+    #[must_use]
+    pub fn report(&self) -> Report {
+        let totals = self.sorted_totals();
+        Report {
+            leaderboard: self.leaderboard(),
+            statistics: Statistics {
+                elves: self.elves.len(),
+                items: self.elves.iter().map(|elf| elf.items.len()).sum(),
+                min: totals.first().copied(),
+                max: totals.last().copied(),
+                mean: self.mean(),
+                median: self.median(),
+                p25: self.percentile(25.0),
+                p75: self.percentile(75.0),
+                p90: self.percentile(90.0),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub rank: usize,
+    pub elf: usize,
+    pub total: u64,
+    /// number of items the elf is carrying
+    pub items: usize,
+    /// another elf carries exactly as many calories
+    pub tied: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statistics {
+    pub elves: usize,
+    pub items: usize,
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+    pub p25: Option<f64>,
+    pub p75: Option<f64>,
+    pub p90: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub leaderboard: Vec<Entry>,
+    pub statistics: Statistics,
+}
+
+impl Report {
+    #[must_use]
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Text => self.to_string(),
+            Format::Json => self.to_json(),
+        }
+    }
+
+    fn to_json(&self) -> String {
+        fn number<T: Display>(value: Option<T>) -> String {
+            value.map_or_else(|| "null".to_string(), |value| value.to_string())
+        }
+        let statistics = &self.statistics;
+        let mut json = format!(
+            r#"{{"statistics":{{"elves":{},"items":{},"min":{},"max":{},"mean":{},"median":{},"p25":{},"p75":{},"p90":{}}},"leaderboard":["#,
+            statistics.elves,
+            statistics.items,
+            number(statistics.min),
+            number(statistics.max),
+            number(statistics.mean),
+            number(statistics.median),
+            number(statistics.p25),
+            number(statistics.p75),
+            number(statistics.p90),
+        );
+        for (index, entry) in self.leaderboard.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                r#"{{"rank":{},"elf":{},"total":{},"items":{},"tied":{}}}"#,
+                entry.rank, entry.elf, entry.total, entry.items, entry.tied
+            );
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn number<T: Display>(value: Option<T>) -> String {
+            value.map_or_else(|| "-".to_string(), |value| format!("{value:.1}"))
+        }
+        writeln!(
+            f,
+            "{:>5} {:>5} {:>12} {:>6}",
+            "rank", "elf", "calories", "items"
+        )?;
+        for entry in &self.leaderboard {
+            let rank = if entry.tied {
+                format!("={}", entry.rank)
+            } else {
+                entry.rank.to_string()
+            };
+            writeln!(
+                f,
+                "{:>5} {:>5} {:>12} {:>6}",
+                rank, entry.elf, entry.total, entry.items
+            )?;
+        }
+        let statistics = &self.statistics;
+        writeln!(f, "elves: {} items: {}", statistics.elves, statistics.items)?;
+        writeln!(
+            f,
+            "mean: {} median: {} p25: {} p75: {} p90: {}",
+            number(statistics.mean),
+            number(statistics.median),
+            number(statistics.p25),
+            number(statistics.p75),
+            number(statistics.p90)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Entry, Inventory};
+    use crate::{day_1::Error, output::Format};
+
+    const EXAMPLE_INPUT: &str = r"
+1000
+2000
+3000
+
+4000
+
+5000
+6000
+
+7000
+8000
+9000
+
+10000";
+
+    #[test]
+    fn example() {
+        let inventory = Inventory::parse(EXAMPLE_INPUT).unwrap();
+        assert_eq!(inventory.elves.len(), 5);
+        assert_eq!(inventory.elves[3].number, 4);
+        assert_eq!(inventory.elves[3].line, 11);
+        assert_eq!(inventory.elves[3].items, vec![7000, 8000, 9000]);
+
+        let leaderboard = inventory.leaderboard();
+        assert_eq!(
+            leaderboard[0],
+            Entry {
+                rank: 1,
+                elf: 4,
+                total: 24000,
+                items: 3,
+                tied: false,
+            }
+        );
+        let elves = leaderboard
+            .iter()
+            .map(|entry| entry.elf)
+            .collect::<Vec<usize>>();
+        assert_eq!(elves, vec![4, 3, 5, 1, 2]);
+
+        assert_eq!(inventory.mean(), Some(11000.0));
+        assert_eq!(inventory.median(), Some(10000.0));
+        assert_eq!(inventory.percentile(0.0), Some(4000.0));
+        assert_eq!(inventory.percentile(100.0), Some(24000.0));
+        assert_eq!(inventory.percentile(25.0), Some(6000.0));
+        assert_eq!(inventory.percentile(87.5), Some(17500.0));
+    }
+
+    #[test]
+    fn ties() {
+        let inventory = Inventory::parse("5\n\n3\n2\n\n9\n\n1\n4").unwrap();
+        let ranks = inventory
+            .leaderboard()
+            .iter()
+            .map(|entry| (entry.rank, entry.elf, entry.tied))
+            .collect::<Vec<(usize, usize, bool)>>();
+        assert_eq!(
+            ranks,
+            vec![(1, 3, false), (2, 1, true), (2, 2, true), (2, 4, true),]
+        );
+    }
+
+    #[test]
+    fn render() {
+        let report = Inventory::parse("1\n2\n\n3").unwrap().report();
+        assert_eq!(
+            report.render(Format::Json),
+            r#"{"statistics":{"elves":2,"items":3,"min":3,"max":3,"mean":3,"median":3,"p25":3,"p75":3,"p90":3},"leaderboard":[{"rank":1,"elf":1,"total":3,"items":2,"tied":true},{"rank":1,"elf":2,"total":3,"items":1,"tied":true}]}"#
+        );
+        let text = report.render(Format::Text);
+        assert!(text.contains("   =1     1            3      2"));
+        assert!(text.contains("mean: 3.0 median: 3.0"));
+
+        let empty = Inventory::default().report();
+        assert!(empty.render(Format::Json).contains(r#""mean":null"#));
+        assert!(empty.render(Format::Text).contains("mean: -"));
+    }
+
+    #[test]
+    fn overflow() {
+        let input = format!("1\n\n{}\n1", u64::MAX);
+        assert!(matches!(
+            Inventory::parse(&input),
+            Err(Error::CaloriesOverflow { line: 4 })
+        ));
+    }
+}
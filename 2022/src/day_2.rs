@@ -33,12 +33,53 @@
 //!
 //! Following the Elf's instructions for the second column, what would your total score be if everything goes exactly according to your strategy guide?
 
+pub mod advisor;
+pub mod game;
+pub mod solver;
+pub mod tournament;
+
+use std::fmt::Display;
+
+use game::{cyclic_outcome, Game, SymbolMapping};
+
 #[derive(Debug)]
 pub enum Error {
     CannotCreateShape,
     CannotCreateRoundOutcome,
+    InvalidGame,
+    /// line is not two space separated characters
+    MalformedLine,
+    /// game with more shapes than [`solver::MAX_SHAPES`] to try every reading of
+    TooManyShapes(usize),
+    Line {
+        number: usize,
+        content: String,
+        error: Box<Error>,
+    },
 }
 
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::CannotCreateShape => write!(f, "unknown shape"),
+            Error::CannotCreateRoundOutcome => write!(f, "unknown round outcome"),
+            Error::InvalidGame => write!(f, "invalid game definition"),
+            Error::MalformedLine => write!(f, "expected two space separated characters"),
+            Error::TooManyShapes(shapes) => write!(
+                f,
+                "{shapes} shapes have too many readings to rank, at most {} are supported",
+                solver::MAX_SHAPES
+            ),
+            Error::Line {
+                number,
+                content,
+                error,
+            } => write!(f, "line {number}: {error}, received {content:?}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DecryptionMethod {
     NextAction,
     NextOutcome,
@@ -77,55 +118,64 @@ impl TryFrom<char> for Shape {
 }
 
 impl Shape {
+    /// Index of the shape in [`Game::rock_paper_scissors`]
     #[must_use]
-    pub fn battle(&self, me: &Self) -> RoundOutcome {
-        use RoundOutcome as RO;
-        let opponent = self;
-        match me {
-            Self::Rock => match opponent {
-                Self::Rock => RO::Draw,
-                Self::Paper => RO::Loss,
-                Self::Scissors => RO::Victory,
-            },
-            Self::Paper => match opponent {
-                Self::Paper => RO::Draw,
-                Self::Scissors => RO::Loss,
-                Self::Rock => RO::Victory,
-            },
-            Self::Scissors => match opponent {
-                Self::Scissors => RO::Draw,
-                Self::Rock => RO::Loss,
-                Self::Paper => RO::Victory,
-            },
+    pub fn index(&self) -> usize {
+        self.clone() as usize - 1
+    }
+
+    /// Inverse of [`Shape::index`]
+    #[must_use]
+    pub fn from_index(index: usize) -> Option<Self> {
+        match index {
+            0 => Some(Self::Rock),
+            1 => Some(Self::Paper),
+            2 => Some(Self::Scissors),
+            _ => None,
         }
     }
 
+    #[must_use]
+    pub fn battle(&self, me: &Self) -> RoundOutcome {
+        cyclic_outcome(3, self.index(), me.index())
+    }
+
     #[must_use]
     pub fn get_shape(&self, wanted_outcome: &RoundOutcome) -> Self {
         use RoundOutcome as RO;
-        let opponent = self;
-        match wanted_outcome {
-            RO::Draw => opponent.clone(),
-            RO::Loss => match opponent {
-                Shape::Rock => Shape::Scissors,
-                Shape::Paper => Shape::Rock,
-                Shape::Scissors => Shape::Paper,
-            },
-            RO::Victory => match opponent {
-                Shape::Rock => Shape::Paper,
-                Shape::Paper => Shape::Scissors,
-                Shape::Scissors => Shape::Rock,
-            },
+        let opponent = self.index();
+        let steps = match wanted_outcome {
+            RO::Draw => 0,
+            RO::Victory => 1,
+            RO::Loss => 2,
+        };
+        match (opponent + steps) % 3 {
+            0 => Self::Rock,
+            1 => Self::Paper,
+            _ => Self::Scissors,
         }
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum RoundOutcome {
     Loss = 0,
     Draw = 3,
     Victory = 6,
 }
 
+impl RoundOutcome {
+    /// The same round seen from the other player
+    #[must_use]
+    pub fn reversed(self) -> Self {
+        match self {
+            Self::Loss => Self::Victory,
+            Self::Draw => Self::Draw,
+            Self::Victory => Self::Loss,
+        }
+    }
+}
+
 impl TryFrom<char> for RoundOutcome {
     type Error = Error;
 
@@ -152,54 +202,108 @@ impl TryFrom<i32> for RoundOutcome {
     }
 }
 
-/// # Panics
+/// One line of a strategy guide. Shapes are indices into the [`Game`] it was played with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoundRecord {
+    /// 1-based line number in the strategy guide
+    pub line: usize,
+    pub opponent: usize,
+    pub player: usize,
+    /// Outcome for the player
+    pub outcome: RoundOutcome,
+    pub opponent_points: i32,
+    pub player_points: i32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Summary {
+    pub rounds: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub opponent_points: i32,
+    pub player_points: i32,
+}
+
+impl From<&[RoundRecord]> for Summary {
+    fn from(records: &[RoundRecord]) -> Self {
+        records.iter().fold(Self::default(), |mut summary, record| {
+            summary.rounds += 1;
+            match record.outcome {
+                RoundOutcome::Loss => summary.losses += 1,
+                RoundOutcome::Draw => summary.draws += 1,
+                RoundOutcome::Victory => summary.wins += 1,
+            }
+            summary.opponent_points += record.opponent_points;
+            summary.player_points += record.player_points;
+            summary
+        })
+    }
+}
+
+fn decrypt_line(line: &str, game: &Game, mapping: &SymbolMapping) -> Result<(usize, usize), Error> {
+    // extract first and last character
+    let (first_char, last_char) = match &line.chars().collect::<Vec<char>>() as &[char] {
+        [first_char, ' ', last_char] => (*first_char, *last_char),
+        _ => return Err(Error::MalformedLine),
+    };
+
+    // convert characters into shapes
+    let opponent = mapping.opponent(first_char)?;
+    let player = mapping.player(game, opponent, last_char)?;
+    Ok((opponent, player))
+}
+
+/// Decrypts every round of the strategy guide in `input`.
 ///
 /// Valid input is new line separated lines where each line contains two space separated character.
-/// First character can only be one of 'A', 'B' or 'C'
-/// Second character can only by one of 'X', 'Y' or 'Z'
+/// First character must be a symbol of the first column of `mapping`.
+/// Second character must be a symbol of the second column of `mapping`.
 /// Empty lines are valid but will be ignored.
 /// lines will be trimmed so prepended and appended white space is valid and will be ignored.
-/// panics on invalid input
 ///
-#[must_use]
-pub fn decrypt(input: &str, decryption_method: &DecryptionMethod) -> i32 {
-    let (_opponent_points, player_points) = input
+/// # Errors
+/// [`Error::Line`] with the first line that is not valid
+pub fn decrypt_rounds(
+    input: &str,
+    game: &Game,
+    mapping: &SymbolMapping,
+) -> Result<Vec<RoundRecord>, Error> {
+    input
         .lines()
-        .map(str::trim)
-        .skip_while(|line| line.is_empty())
-        // calculate points for each round
-        .fold((0, 0), |(opponent_total, player_total), string| {
-            // points this round
-            let (opponent, player) = play({
-                // extract first and last character
-                let (first_char, last_char) =
-                    match &string.chars().collect::<Vec<char>>() as &[char] {
-                        [first_char, ' ', last_char] => (*first_char, *last_char),
-                        trash => {
-                            panic!(
-                                "expected two space separated characters, received {:#?}",
-                                trash
-                            )
-                        }
-                    };
-
-                // convert character into Shapes
-                let opponent = Shape::try_from(first_char).unwrap();
-                let player = match decryption_method {
-                    DecryptionMethod::NextAction => Shape::try_from(last_char).unwrap(),
-                    DecryptionMethod::NextOutcome => {
-                        let wanted_outcome = RoundOutcome::try_from(last_char).unwrap();
-                        opponent.get_shape(&wanted_outcome)
-                    }
-                };
-
-                // use shapes and calculate outcome
-                (opponent, player)
-            });
-
-            (opponent_total + opponent, player_total + player)
-        });
-    player_points
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(number, line)| {
+            let (opponent, player) =
+                decrypt_line(line, game, mapping).map_err(|error| Error::Line {
+                    number,
+                    content: line.to_string(),
+                    error: Box::new(error),
+                })?;
+            let (opponent_points, player_points) = game.play(opponent, player);
+            Ok(RoundRecord {
+                line: number,
+                opponent,
+                player,
+                outcome: game.battle(opponent, player),
+                opponent_points,
+                player_points,
+            })
+        })
+        .collect()
+}
+
+/// Total points for the player. See [`decrypt_rounds`] for the input format.
+///
+/// # Panics
+/// panics on invalid input
+#[must_use]
+pub fn decrypt(input: &str, game: &Game, mapping: &SymbolMapping) -> i32 {
+    match decrypt_rounds(input, game, mapping) {
+        Ok(records) => Summary::from(records.as_slice()).player_points,
+        Err(error) => panic!("{error}"),
+    }
 }
 
 #[must_use]
@@ -218,7 +322,11 @@ pub fn play((p1, p2): (Shape, Shape)) -> (i32, i32) {
 #[cfg(test)]
 mod tests {
     use super::RoundOutcome as RO;
-    use crate::day_2::{decrypt, DecryptionMethod as DM, Shape};
+    use crate::day_2::{
+        decrypt as decrypt_with, decrypt_rounds,
+        game::{Game, SymbolMapping},
+        DecryptionMethod as DM, Error, RoundRecord, Shape, Summary,
+    };
 
     const INPUT: &str = include_str!("../puzzle_input/day_2.txt");
     const EXAMPLE_INPUT: &str = r"
@@ -228,6 +336,23 @@ C Z";
     const ANSWER: [i32; 2] = [10595, 9541];
     const EXAMPLE_ANSWER: [i32; 2] = [15, 12];
 
+    fn decrypt(input: &str, method: &DM) -> i32 {
+        decrypt_with(input, &Game::rock_paper_scissors(), &method.into())
+    }
+
+    #[test]
+    fn get_shape() {
+        for opponent in [Shape::Rock, Shape::Paper, Shape::Scissors] {
+            for outcome in [RO::Loss, RO::Draw, RO::Victory] {
+                assert_eq!(opponent.battle(&opponent.get_shape(&outcome)), outcome);
+            }
+        }
+        assert_eq!(
+            SymbolMapping::from(&DM::NextAction),
+            SymbolMapping::shapes("abc", "xyz")
+        );
+    }
+
     #[test]
     fn types() {
         assert_eq!(Shape::Rock as i32, 1);
@@ -308,4 +433,58 @@ C Z";
             assert_eq!(points, ANSWER[index]);
         }
     }
+
+    #[test]
+    fn rounds() {
+        let game = Game::rock_paper_scissors();
+        let records = decrypt_rounds(EXAMPLE_INPUT, &game, &(&DM::NextAction).into()).unwrap();
+
+        assert_eq!(
+            records[0],
+            RoundRecord {
+                line: 2,
+                opponent: Shape::Rock.index(),
+                player: Shape::Paper.index(),
+                outcome: RO::Victory,
+                opponent_points: 1,
+                player_points: 8,
+            }
+        );
+        assert_eq!(
+            Summary::from(records.as_slice()),
+            Summary {
+                rounds: 3,
+                wins: 1,
+                draws: 1,
+                losses: 1,
+                opponent_points: 15,
+                player_points: 15,
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_lines() {
+        let game = Game::rock_paper_scissors();
+        let mapping = (&DM::NextOutcome).into();
+
+        let error = decrypt_rounds("A X\n\nB W\nC Z", &game, &mapping).unwrap_err();
+        match &error {
+            Error::Line {
+                number, content, ..
+            } => assert_eq!((*number, content.as_str()), (3, "B W")),
+            _ => panic!("expected a line error, received {error:?}"),
+        }
+        assert_eq!(
+            error.to_string(),
+            "line 3: unknown round outcome, received \"B W\""
+        );
+
+        for input in ["AX", "A  X", "D X"] {
+            assert!(matches!(
+                decrypt_rounds(input, &game, &mapping),
+                Err(Error::Line { number: 1, .. })
+            ));
+        }
+    }
 }
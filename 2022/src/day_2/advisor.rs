@@ -0,0 +1,253 @@
+//! Is a strategy guide any good?
+//!
+//! Without a guide the best one can do against an opponent is to play the response with the
+//! highest expected score given how often the opponent picks each shape. A guide that scores
+//! less than that response is not helping, however convincing it looks.
+
+use super::{
+    decrypt_rounds,
+    game::{Game, SymbolMapping},
+    Error, Summary,
+};
+
+/// A probability for every shape of a game
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub weights: Vec<f64>,
+    /// Expected points per round against the opponent distribution
+    pub expected_points: f64,
+}
+
+impl Response {
+    /// The shape to play when the response is not mixed
+    #[must_use]
+    pub fn pure(&self) -> Option<usize> {
+        match self.support().as_slice() {
+            [shape] => Some(*shape),
+            _ => None,
+        }
+    }
+
+    /// Shapes played with a non zero probability
+    #[must_use]
+    pub fn support(&self) -> Vec<usize> {
+        (0..self.weights.len())
+            .filter(|&shape| self.weights[shape] > 0.0)
+            .collect()
+    }
+}
+
+/// Expected points per round for playing `shape` against `distribution`.
+#[must_use]
+pub fn expected_points(game: &Game, distribution: &[f64], shape: usize) -> f64 {
+    let scores = game.outcome_scores();
+    distribution
+        .iter()
+        .enumerate()
+        .map(|(opponent, probability)| {
+            let points = game.shape_score(shape) + scores.get(game.battle(opponent, shape));
+            probability * f64::from(points)
+        })
+        .sum()
+}
+
+/// The expected score is linear in the weights of a mixed response, so no mixture can beat the
+/// best pure shape. When several shapes tie they are mixed uniformly; any other mixture of them
+/// is just as good.
+#[must_use]
+pub fn best_response(game: &Game, distribution: &[f64]) -> Response {
+    const EPSILON: f64 = 1e-9;
+
+    let expected = (0..game.len())
+        .map(|shape| expected_points(game, distribution, shape))
+        .collect::<Vec<f64>>();
+    let best = expected.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let ties = expected
+        .iter()
+        .filter(|&&points| best - points < EPSILON)
+        .count();
+
+    let weight = 1.0 / f64::from(u32::try_from(ties).unwrap_or(u32::MAX));
+    let weights = expected
+        .iter()
+        .map(|&points| if best - points < EPSILON { weight } else { 0.0 })
+        .collect();
+
+    Response {
+        weights,
+        expected_points: best,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub rounds: u32,
+    /// How often the opponent picks each shape according to the guide
+    pub distribution: Vec<f64>,
+    pub best_response: Response,
+    /// Points from the best response over all rounds
+    pub best_response_points: f64,
+    /// Sum over the rounds of the most points any shape scores against the opponent's shape,
+    /// the most any guide can give with the game's scores
+    pub upper_bound: i32,
+    /// Points from following the guide, one entry per mapping in the order they were given
+    pub guide_points: Vec<i32>,
+}
+
+impl Evaluation {
+    /// The guide read with the `mapping`-th mapping beats playing the best response without it
+    #[must_use]
+    pub fn is_helpful(&self, mapping: usize) -> bool {
+        self.guide_points
+            .get(mapping)
+            .is_some_and(|&points| f64::from(points) > self.best_response_points)
+    }
+}
+
+/// Compares the strategy guide in `input` read with each of `mappings` against the best
+/// response to the opponent's column. The opponent's column is read with the first mapping,
+/// without mappings there are no rounds.
+///
+/// # Errors
+/// on lines that [`decrypt_rounds`] can not read
+pub fn evaluate(input: &str, game: &Game, mappings: &[SymbolMapping]) -> Result<Evaluation, Error> {
+    let mut guide_points = vec![];
+    let mut counts = vec![0_u32; game.len()];
+    let mut rounds = 0;
+    let mut upper_bound = 0;
+
+    for (index, mapping) in mappings.iter().enumerate() {
+        let records = decrypt_rounds(input, game, mapping)?;
+        guide_points.push(Summary::from(records.as_slice()).player_points);
+
+        if index == 0 {
+            for record in &records {
+                rounds += 1;
+                counts[record.opponent] += 1;
+                upper_bound += (0..game.len())
+                    .map(|shape| game.play(record.opponent, shape).1)
+                    .max()
+                    .unwrap_or(0);
+            }
+        }
+    }
+
+    let distribution = counts
+        .iter()
+        .map(|&count| f64::from(count) / f64::from(rounds.max(1)))
+        .collect::<Vec<f64>>();
+    let best_response = best_response(game, &distribution);
+
+    Ok(Evaluation {
+        rounds,
+        best_response_points: best_response.expected_points * f64::from(rounds),
+        distribution,
+        best_response,
+        upper_bound,
+        guide_points,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{best_response, evaluate, expected_points};
+    use crate::day_2::{
+        game::{Game, SymbolMapping},
+        DecryptionMethod as DM,
+    };
+
+    const INPUT: &str = include_str!("../../puzzle_input/day_2.txt");
+    const EXAMPLE_INPUT: &str = r"
+A Y
+B X
+C Z";
+
+    fn readings() -> Vec<SymbolMapping> {
+        vec![
+            SymbolMapping::from(&DM::NextAction),
+            SymbolMapping::from(&DM::NextOutcome),
+        ]
+    }
+
+    #[test]
+    fn response() {
+        let game = Game::rock_paper_scissors();
+        let [rock, paper, scissors] = [0, 1, 2];
+
+        // always rock: paper wins for 2 + 6
+        let response = best_response(&game, &[1.0, 0.0, 0.0]);
+        assert_eq!(response.pure(), Some(paper));
+        assert!((response.expected_points - 8.0).abs() < 1e-9);
+
+        // uniform opponent: every shape wins, draws and loses equally often,
+        // so the shape worth the most points is best
+        let uniform = [1.0 / 3.0; 3];
+        assert!((expected_points(&game, &uniform, rock) - 4.0).abs() < 1e-9);
+        assert_eq!(best_response(&game, &uniform).pure(), Some(scissors));
+
+        // equal points for every shape makes every shape equally good
+        let flat = game.with_shape_scores(&[0, 0, 0]).unwrap();
+        let response = best_response(&flat, &uniform);
+        assert_eq!(response.pure(), None);
+        assert_eq!(response.support(), vec![rock, paper, scissors]);
+    }
+
+    #[test]
+    fn example() {
+        // a second reading of the shapes: X=Scissors Y=Paper Z=Rock wins every round
+        let mut mappings = readings();
+        mappings.push(SymbolMapping::shapes("ABC", "ZYX"));
+        let evaluation = evaluate(EXAMPLE_INPUT, &Game::rock_paper_scissors(), &mappings).unwrap();
+
+        assert_eq!(evaluation.rounds, 3);
+        assert_eq!(evaluation.guide_points, vec![15, 12, 8 + 9 + 7]);
+        assert_eq!(evaluation.upper_bound, 8 + 9 + 7);
+        // scissors: loss against rock, win against paper, draw against scissors
+        assert_eq!(evaluation.best_response.pure(), Some(2));
+        assert!((evaluation.best_response_points - f64::from(3 * 3 + 6 + 3)).abs() < 1e-9);
+        assert!(!evaluation.is_helpful(0));
+        assert!(!evaluation.is_helpful(1));
+        assert!(evaluation.is_helpful(2));
+        assert!(!evaluation.is_helpful(3));
+    }
+
+    #[test]
+    fn problem() {
+        let evaluation = evaluate(INPUT, &Game::rock_paper_scissors(), &readings()).unwrap();
+
+        assert_eq!(evaluation.rounds, 2500);
+        assert_eq!(evaluation.guide_points, vec![10595, 9541]);
+        assert!((evaluation.distribution.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        for &points in &evaluation.guide_points {
+            assert!(points <= evaluation.upper_bound);
+        }
+    }
+
+    #[test]
+    fn custom_mapping_and_scores() {
+        // scissors is worth so much that losing with it beats winning with anything else
+        let game = Game::rock_paper_scissors()
+            .with_shape_scores(&[1, 2, 30])
+            .unwrap();
+        let guide = "R S\nP S\nS S";
+        let evaluation = evaluate(
+            guide,
+            &game,
+            &[
+                SymbolMapping::shapes("RPS", "RPS"),
+                SymbolMapping::outcomes("RPS", ['L', 'D', 'S']),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(evaluation.rounds, 3);
+        assert_eq!(evaluation.guide_points, vec![30 + 36 + 33, 8 + 36 + 7]);
+        assert_eq!(evaluation.upper_bound, 30 + 36 + 33);
+        // always scissors is the best response too, so the guide only ties with it
+        assert_eq!(evaluation.best_response.pure(), Some(2));
+        assert!((evaluation.best_response_points - 99.0).abs() < 1e-9);
+        assert!(!evaluation.is_helpful(0));
+
+        assert_eq!(evaluate(guide, &game, &[]).unwrap().rounds, 0);
+    }
+}
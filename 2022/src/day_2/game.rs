@@ -0,0 +1,439 @@
+//! Generalized hand games.
+//!
+//! Rock Paper Scissors is the smallest member of a family of cyclic dominance games: every shape
+//! beats exactly half of the other shapes and loses against the rest. A [`Game`] describes one of
+//! these games (or any game given by a win matrix) together with the points awarded for the
+//! chosen shape and for the outcome of a round. Shapes are referred to by their index in the game.
+
+use std::collections::HashMap;
+
+use super::{DecryptionMethod, Error, RoundOutcome};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game {
+    names: Vec<String>,
+    shape_scores: Vec<i32>,
+    outcome_scores: OutcomeScores,
+    /// `matrix[me][opponent]` is the outcome of the round seen from `me`
+    matrix: Vec<Vec<RoundOutcome>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutcomeScores {
+    pub loss: i32,
+    pub draw: i32,
+    pub victory: i32,
+}
+
+impl Default for OutcomeScores {
+    fn default() -> Self {
+        Self {
+            loss: RoundOutcome::Loss as i32,
+            draw: RoundOutcome::Draw as i32,
+            victory: RoundOutcome::Victory as i32,
+        }
+    }
+}
+
+impl OutcomeScores {
+    #[must_use]
+    pub fn get(&self, outcome: RoundOutcome) -> i32 {
+        match outcome {
+            RoundOutcome::Loss => self.loss,
+            RoundOutcome::Draw => self.draw,
+            RoundOutcome::Victory => self.victory,
+        }
+    }
+}
+
+/// Outcome of `me` against `opponent` in a cyclic game with `size` shapes.
+/// Shape `me` beats every shape an odd number of steps behind it in the cycle.
+pub(crate) fn cyclic_outcome(size: usize, opponent: usize, me: usize) -> RoundOutcome {
+    match (me + size - opponent) % size {
+        0 => RoundOutcome::Draw,
+        steps if steps % 2 == 1 => RoundOutcome::Victory,
+        _ => RoundOutcome::Loss,
+    }
+}
+
+fn cyclic_matrix(size: usize) -> Vec<Vec<RoundOutcome>> {
+    (0..size)
+        .map(|me| {
+            (0..size)
+                .map(|opponent| cyclic_outcome(size, opponent, me))
+                .collect()
+        })
+        .collect()
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::rock_paper_scissors()
+    }
+}
+
+impl Game {
+    /// The game from the puzzle: `Rock`, `Paper` and `Scissors` worth 1, 2 and 3 points.
+    #[must_use]
+    pub fn rock_paper_scissors() -> Self {
+        Self::build(&["Rock", "Paper", "Scissors"], cyclic_matrix(3))
+    }
+
+    /// `Rock`, `Paper`, `Scissors`, `Spock` and `Lizard` worth 1 to 5 points.
+    #[must_use]
+    pub fn rock_paper_scissors_spock_lizard() -> Self {
+        let names = ["Rock", "Paper", "Scissors", "Spock", "Lizard"];
+        Self::build(&names, cyclic_matrix(5))
+    }
+
+    /// Creates a cyclic dominance game where each shape beats the shapes an odd number of steps
+    /// behind it in `names`. Shape `n` is worth `n + 1` points.
+    ///
+    /// # Errors
+    /// if `names` has fewer than three or an even number of shapes
+    pub fn cyclic(names: &[&str]) -> Result<Self, Error> {
+        let size = names.len();
+        if size < 3 || size.is_multiple_of(2) {
+            return Err(Error::InvalidGame);
+        }
+        Ok(Self::build(names, cyclic_matrix(size)))
+    }
+
+    /// Creates a game from a user supplied win matrix where `matrix[me][opponent]` is the outcome
+    /// for `me`. Shape `n` is worth `n + 1` points.
+    ///
+    /// # Errors
+    /// if the matrix is not square with one row per name, if a shape does not draw against
+    /// itself or if two shapes disagree on the outcome of their round
+    pub fn from_matrix(names: &[&str], matrix: Vec<Vec<RoundOutcome>>) -> Result<Self, Error> {
+        let size = names.len();
+        if size == 0 || matrix.len() != size || matrix.iter().any(|row| row.len() != size) {
+            return Err(Error::InvalidGame);
+        }
+        for (me, row) in matrix.iter().enumerate() {
+            for (opponent, outcome) in row.iter().enumerate() {
+                let consistent = match (*outcome, matrix[opponent][me]) {
+                    (RoundOutcome::Draw, RoundOutcome::Draw) => me == opponent,
+                    (RoundOutcome::Victory, RoundOutcome::Loss)
+                    | (RoundOutcome::Loss, RoundOutcome::Victory) => true,
+                    _ => false,
+                };
+                if !consistent {
+                    return Err(Error::InvalidGame);
+                }
+            }
+        }
+        Ok(Self::build(names, matrix))
+    }
+
+    fn build(names: &[&str], matrix: Vec<Vec<RoundOutcome>>) -> Self {
+        Self {
+            names: names.iter().map(ToString::to_string).collect(),
+            shape_scores: (1..).take(names.len()).collect(),
+            outcome_scores: OutcomeScores::default(),
+            matrix,
+        }
+    }
+
+    /// Replaces the points awarded for each shape.
+    ///
+    /// # Errors
+    /// if there is not exactly one score per shape
+    pub fn with_shape_scores(mut self, shape_scores: &[i32]) -> Result<Self, Error> {
+        if shape_scores.len() != self.len() {
+            return Err(Error::InvalidGame);
+        }
+        self.shape_scores = shape_scores.to_vec();
+        Ok(self)
+    }
+
+    /// Replaces the points awarded for the outcome of a round.
+    #[must_use]
+    pub fn with_outcome_scores(mut self, outcome_scores: OutcomeScores) -> Self {
+        self.outcome_scores = outcome_scores;
+        self
+    }
+
+    /// Number of shapes in the game
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    #[must_use]
+    pub fn name(&self, shape: usize) -> &str {
+        &self.names[shape]
+    }
+
+    #[must_use]
+    pub fn shape_score(&self, shape: usize) -> i32 {
+        self.shape_scores[shape]
+    }
+
+    #[must_use]
+    pub fn outcome_scores(&self) -> OutcomeScores {
+        self.outcome_scores
+    }
+
+    /// Outcome of a round seen from `me`.
+    #[must_use]
+    pub fn battle(&self, opponent: usize, me: usize) -> RoundOutcome {
+        self.matrix[me][opponent]
+    }
+
+    /// Returns the shape that gives `wanted_outcome` against `opponent`. When several shapes give
+    /// the same outcome the one worth the most points is picked, the lowest index on ties.
+    #[must_use]
+    pub fn get_shape(&self, opponent: usize, wanted_outcome: RoundOutcome) -> Option<usize> {
+        (0..self.len())
+            .filter(|&me| self.battle(opponent, me) == wanted_outcome)
+            .rev()
+            .max_by_key(|&me| self.shape_score(me))
+    }
+
+    /// Points for the opponent and for me in one round.
+    #[must_use]
+    pub fn play(&self, opponent: usize, me: usize) -> (i32, i32) {
+        let outcome = self.battle(opponent, me);
+        (
+            self.shape_score(opponent) + self.outcome_scores.get(outcome.reversed()),
+            self.shape_score(me) + self.outcome_scores.get(outcome),
+        )
+    }
+}
+
+/// What the second column of a strategy guide means.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayerColumn {
+    Shape(HashMap<char, usize>),
+    Outcome(HashMap<char, RoundOutcome>),
+}
+
+/// Translates the symbols of a strategy guide into shapes and outcomes of a [`Game`].
+/// Symbols are case insensitive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolMapping {
+    opponent: HashMap<char, usize>,
+    player: PlayerColumn,
+}
+
+impl SymbolMapping {
+    #[must_use]
+    pub fn new(opponent: HashMap<char, usize>, player: PlayerColumn) -> Self {
+        let opponent = opponent
+            .into_iter()
+            .map(|(symbol, shape)| (symbol.to_ascii_uppercase(), shape))
+            .collect();
+        let player = match player {
+            PlayerColumn::Shape(map) => PlayerColumn::Shape(
+                map.into_iter()
+                    .map(|(symbol, shape)| (symbol.to_ascii_uppercase(), shape))
+                    .collect(),
+            ),
+            PlayerColumn::Outcome(map) => PlayerColumn::Outcome(
+                map.into_iter()
+                    .map(|(symbol, outcome)| (symbol.to_ascii_uppercase(), outcome))
+                    .collect(),
+            ),
+        };
+        Self { opponent, player }
+    }
+
+    /// Both columns name shapes: the n-th symbol of each string is shape n.
+    #[must_use]
+    pub fn shapes(opponent: &str, player: &str) -> Self {
+        Self::new(
+            opponent.chars().zip(0..).collect(),
+            PlayerColumn::Shape(player.chars().zip(0..).collect()),
+        )
+    }
+
+    /// The first column names shapes, the second names the loss, draw and victory symbols.
+    #[must_use]
+    pub fn outcomes(opponent: &str, [loss, draw, victory]: [char; 3]) -> Self {
+        Self::new(
+            opponent.chars().zip(0..).collect(),
+            PlayerColumn::Outcome(HashMap::from([
+                (loss, RoundOutcome::Loss),
+                (draw, RoundOutcome::Draw),
+                (victory, RoundOutcome::Victory),
+            ])),
+        )
+    }
+
+    /// How the second column is read
+    #[must_use]
+    pub fn decryption_method(&self) -> DecryptionMethod {
+        match self.player {
+            PlayerColumn::Shape(_) => DecryptionMethod::NextAction,
+            PlayerColumn::Outcome(_) => DecryptionMethod::NextOutcome,
+        }
+    }
+
+    /// # Errors
+    /// if `symbol` is not part of the first column
+    pub fn opponent(&self, symbol: char) -> Result<usize, Error> {
+        self.opponent
+            .get(&symbol.to_ascii_uppercase())
+            .copied()
+            .ok_or(Error::CannotCreateShape)
+    }
+
+    /// Shape to play against `opponent` when the guide says `symbol`.
+    ///
+    /// # Errors
+    /// if `symbol` is not part of the second column, no shape gives the wanted outcome or a shape
+    /// is not part of `game`
+    pub fn player(&self, game: &Game, opponent: usize, symbol: char) -> Result<usize, Error> {
+        if opponent >= game.len() {
+            return Err(Error::CannotCreateShape);
+        }
+        let symbol = symbol.to_ascii_uppercase();
+        match &self.player {
+            PlayerColumn::Shape(map) => map
+                .get(&symbol)
+                .copied()
+                .filter(|&shape| shape < game.len())
+                .ok_or(Error::CannotCreateShape),
+            PlayerColumn::Outcome(map) => {
+                let wanted_outcome = map
+                    .get(&symbol)
+                    .copied()
+                    .ok_or(Error::CannotCreateRoundOutcome)?;
+                game.get_shape(opponent, wanted_outcome)
+                    .ok_or(Error::CannotCreateShape)
+            }
+        }
+    }
+}
+
+impl From<&DecryptionMethod> for SymbolMapping {
+    fn from(decryption_method: &DecryptionMethod) -> Self {
+        match decryption_method {
+            DecryptionMethod::NextAction => Self::shapes("ABC", "XYZ"),
+            DecryptionMethod::NextOutcome => Self::outcomes("ABC", ['X', 'Y', 'Z']),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Game, OutcomeScores, SymbolMapping};
+    use crate::day_2::{decrypt, Error, RoundOutcome as RO, Shape};
+
+    const EXAMPLE_INPUT: &str = r"
+A Y
+B X
+C Z
+D V
+E W";
+    const EXAMPLE_OUTCOMES: &str = r"
+A Y
+B X
+C Z
+D Z
+E Z";
+
+    #[test]
+    fn preset_matches_shape() {
+        let game = Game::rock_paper_scissors();
+        let shapes = [Shape::Rock, Shape::Paper, Shape::Scissors];
+        for (opponent, opponent_shape) in shapes.iter().enumerate() {
+            for (me, my_shape) in shapes.iter().enumerate() {
+                assert_eq!(game.battle(opponent, me), opponent_shape.battle(my_shape));
+                assert_eq!(
+                    game.play(opponent, me),
+                    crate::day_2::play((opponent_shape.clone(), my_shape.clone()))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn spock_lizard() {
+        let game = Game::rock_paper_scissors_spock_lizard();
+        let [rock, paper, scissors, spock, lizard] = [0, 1, 2, 3, 4];
+
+        assert_eq!(game.battle(scissors, spock), RO::Victory);
+        assert_eq!(game.battle(rock, spock), RO::Victory);
+        assert_eq!(game.battle(spock, lizard), RO::Victory);
+        assert_eq!(game.battle(paper, lizard), RO::Victory);
+        assert_eq!(game.battle(lizard, rock), RO::Victory);
+        assert_eq!(game.battle(lizard, scissors), RO::Victory);
+        assert_eq!(game.battle(spock, paper), RO::Victory);
+        assert_eq!(game.battle(spock, spock), RO::Draw);
+
+        // both paper and spock beat rock, spock is worth more
+        assert_eq!(game.get_shape(rock, RO::Victory), Some(spock));
+        assert_eq!(game.get_shape(rock, RO::Loss), Some(lizard));
+    }
+
+    #[test]
+    fn invalid_games() {
+        assert!(Game::cyclic(&["Rock", "Paper"]).is_err());
+        assert!(Game::cyclic(&["a", "b", "c", "d"]).is_err());
+        assert!(Game::from_matrix(&["a", "b"], vec![vec![RO::Draw, RO::Draw]; 2]).is_err());
+        assert!(Game::from_matrix(
+            &["a", "b"],
+            vec![vec![RO::Draw, RO::Victory], vec![RO::Victory, RO::Draw]]
+        )
+        .is_err());
+        assert!(Game::rock_paper_scissors()
+            .with_shape_scores(&[1, 2])
+            .is_err());
+    }
+
+    #[test]
+    fn custom_scores() {
+        let game = Game::from_matrix(
+            &["Up", "Down"],
+            vec![vec![RO::Draw, RO::Victory], vec![RO::Loss, RO::Draw]],
+        )
+        .unwrap()
+        .with_shape_scores(&[10, 20])
+        .unwrap()
+        .with_outcome_scores(OutcomeScores {
+            loss: -1,
+            draw: 0,
+            victory: 1,
+        });
+        let mapping = SymbolMapping::shapes("ud", "UD");
+
+        assert_eq!(game.play(1, 0), (19, 11));
+        assert_eq!(decrypt("d U\nu u", &game, &mapping), 11 + 10);
+    }
+
+    #[test]
+    fn example() {
+        let game = Game::rock_paper_scissors_spock_lizard();
+        // rock/paper, paper/rock, scissors/scissors, spock/spock, lizard/lizard
+        let shapes = SymbolMapping::shapes("ABCDE", "XYZVW");
+        assert_eq!(decrypt(EXAMPLE_INPUT, &game, &shapes), 8 + 1 + 6 + 7 + 8);
+
+        let outcomes = SymbolMapping::outcomes("ABCDE", ['X', 'Y', 'Z']);
+        // rock draw, paper loss with spock, scissors victory with spock,
+        // spock victory with lizard, lizard victory with scissors
+        let expected = (1 + 3) + 4 + (4 + 6) + (5 + 6) + (3 + 6);
+        assert_eq!(decrypt(EXAMPLE_OUTCOMES, &game, &outcomes), expected);
+    }
+
+    #[test]
+    fn mapping_for_a_larger_game() {
+        let game = Game::rock_paper_scissors();
+        let mapping = SymbolMapping::shapes("ABCDE", "XYZVW");
+        assert!(matches!(mapping.player(&game, 0, 'Z'), Ok(2)));
+        assert!(matches!(
+            mapping.player(&game, 0, 'V'),
+            Err(Error::CannotCreateShape)
+        ));
+        assert!(matches!(
+            mapping.player(&game, 3, 'X'),
+            Err(Error::CannotCreateShape)
+        ));
+    }
+}
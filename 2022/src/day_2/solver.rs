@@ -0,0 +1,258 @@
+//! Tries every way of reading the second column of a strategy guide.
+//!
+//! [`DecryptionMethod`](super::DecryptionMethod) only knows the two readings from the puzzle.
+//! Here every permutation of the second column onto the shapes of a [`Game`], and onto the round
+//! outcomes, is scored. The spread between the best and the worst reading tells how much a guide
+//! depends on being read correctly. A game with n shapes has n! readings, so only games with up
+//! to [`MAX_SHAPES`] shapes are ranked by shape.
+
+use std::fmt::Display;
+
+use super::{
+    decrypt_rounds,
+    game::{Game, PlayerColumn, SymbolMapping},
+    Error, RoundOutcome, Summary,
+};
+
+/// Most shapes [`rank_mappings`] permutes, 8! = 40320 readings
+pub const MAX_SHAPES: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    /// e.g. `X=Rock Y=Paper Z=Scissors` or `X=Loss Y=Draw Z=Victory`
+    pub description: String,
+    pub mapping: SymbolMapping,
+    pub score: i32,
+}
+
+/// All candidates sorted from the highest to the lowest score
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ranking {
+    pub candidates: Vec<Candidate>,
+}
+
+impl Ranking {
+    #[must_use]
+    pub fn best(&self) -> Option<&Candidate> {
+        self.candidates.first()
+    }
+
+    #[must_use]
+    pub fn worst(&self) -> Option<&Candidate> {
+        self.candidates.last()
+    }
+
+    /// Difference between the best and the worst score
+    #[must_use]
+    pub fn spread(&self) -> i32 {
+        match (self.best(), self.worst()) {
+            (Some(best), Some(worst)) => best.score - worst.score,
+            _ => 0,
+        }
+    }
+}
+
+impl Display for Ranking {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (rank, candidate) in self.candidates.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>3}. {:>8} {}",
+                rank + 1,
+                candidate.score,
+                candidate.description
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Every ordering of `0..size` in lexicographic order
+fn permutations(size: usize) -> Vec<Vec<usize>> {
+    if size == 0 {
+        return vec![vec![]];
+    }
+    let mut output = vec![];
+    for first in 0..size {
+        for rest in permutations(size - 1) {
+            let mut permutation = vec![first];
+            permutation.extend(rest.into_iter().map(|i| if i >= first { i + 1 } else { i }));
+            output.push(permutation);
+        }
+    }
+    output
+}
+
+fn describe<T>(player: &str, values: &[T], name: impl Fn(&T) -> String) -> String {
+    player
+        .chars()
+        .zip(values)
+        .map(|(symbol, value)| format!("{symbol}={}", name(value)))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Scores `input` under every reading of the `player` column. The `opponent` column maps the
+/// n-th symbol to shape n of `game`. The second column is read as shapes when `player` has one
+/// symbol per shape and as outcomes when it has three symbols.
+///
+/// # Errors
+/// [`Error::TooManyShapes`] if `player` has one symbol per shape and there are more than
+/// [`MAX_SHAPES`], [`Error::Line`] for the first line that [`decrypt_rounds`] can not read
+pub fn rank_mappings(
+    input: &str,
+    game: &Game,
+    opponent: &str,
+    player: &str,
+) -> Result<Ranking, Error> {
+    let opponent_column = || opponent.chars().zip(0..).collect();
+    let score = |mapping: &SymbolMapping| -> Result<i32, Error> {
+        let records = decrypt_rounds(input, game, mapping)?;
+        Ok(Summary::from(records.as_slice()).player_points)
+    };
+    let symbols = player.chars().count();
+    let mut candidates = vec![];
+
+    if symbols == game.len() {
+        if symbols > MAX_SHAPES {
+            return Err(Error::TooManyShapes(symbols));
+        }
+        for shapes in permutations(symbols) {
+            let mapping = SymbolMapping::new(
+                opponent_column(),
+                PlayerColumn::Shape(player.chars().zip(shapes.iter().copied()).collect()),
+            );
+            candidates.push(Candidate {
+                description: describe(player, &shapes, |&shape| game.name(shape).to_string()),
+                score: score(&mapping)?,
+                mapping,
+            });
+        }
+    }
+
+    if symbols == 3 {
+        let all = [
+            RoundOutcome::Loss,
+            RoundOutcome::Draw,
+            RoundOutcome::Victory,
+        ];
+        for order in permutations(3) {
+            let outcomes = order.iter().map(|&i| all[i]).collect::<Vec<RoundOutcome>>();
+            let mapping = SymbolMapping::new(
+                opponent_column(),
+                PlayerColumn::Outcome(player.chars().zip(outcomes.iter().copied()).collect()),
+            );
+            candidates.push(Candidate {
+                description: describe(player, &outcomes, |outcome| format!("{outcome:?}")),
+                score: score(&mapping)?,
+                mapping,
+            });
+        }
+    }
+
+    candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.score));
+    Ok(Ranking { candidates })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{permutations, rank_mappings, MAX_SHAPES};
+    use crate::day_2::{decrypt, game::Game, DecryptionMethod as DM, Error};
+
+    const INPUT: &str = include_str!("../../puzzle_input/day_2.txt");
+    const EXAMPLE_INPUT: &str = r"
+A Y
+B X
+C Z";
+
+    #[test]
+    fn test_permutations() {
+        assert_eq!(permutations(1), vec![vec![0]]);
+        assert_eq!(
+            permutations(3),
+            vec![
+                vec![0, 1, 2],
+                vec![0, 2, 1],
+                vec![1, 0, 2],
+                vec![1, 2, 0],
+                vec![2, 0, 1],
+                vec![2, 1, 0]
+            ]
+        );
+        assert_eq!(permutations(5).len(), 120);
+    }
+
+    #[test]
+    fn example() {
+        let game = Game::rock_paper_scissors();
+        let ranking = rank_mappings(EXAMPLE_INPUT, &game, "ABC", "XYZ").unwrap();
+
+        assert_eq!(ranking.candidates.len(), 12);
+        let scores = ranking
+            .candidates
+            .iter()
+            .map(|candidate| candidate.score)
+            .collect::<Vec<i32>>();
+        assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+
+        for candidate in &ranking.candidates {
+            assert_eq!(
+                candidate.score,
+                decrypt(EXAMPLE_INPUT, &game, &candidate.mapping)
+            );
+        }
+        let puzzle = |description: &str| {
+            ranking
+                .candidates
+                .iter()
+                .find(|candidate| candidate.description == description)
+                .unwrap()
+                .score
+        };
+        assert_eq!(puzzle("X=Rock Y=Paper Z=Scissors"), 15);
+        assert_eq!(puzzle("X=Loss Y=Draw Z=Victory"), 12);
+
+        // winning all three rounds is the best any reading can do
+        let best = ranking.best().unwrap();
+        assert_eq!(best.score, (2 + 6) + (3 + 6) + (1 + 6));
+        assert_eq!(best.description, "X=Scissors Y=Paper Z=Rock");
+    }
+
+    #[test]
+    fn problem() {
+        let game = Game::rock_paper_scissors();
+        let ranking = rank_mappings(INPUT, &game, "ABC", "XYZ").unwrap();
+        for method in [DM::NextAction, DM::NextOutcome] {
+            let score = decrypt(INPUT, &game, &(&method).into());
+            assert!(ranking.best().unwrap().score >= score);
+            assert!(ranking.worst().unwrap().score <= score);
+        }
+        assert_eq!(ranking.to_string().lines().count(), 12);
+    }
+
+    #[test]
+    fn invalid_input() {
+        let game = Game::rock_paper_scissors();
+        assert!(matches!(
+            rank_mappings("A Y\nB W\n", &game, "ABC", "XYZ"),
+            Err(Error::Line { number: 2, .. })
+        ));
+        assert!(matches!(
+            rank_mappings("A Y\nB\n", &game, "ABC", "XYZ"),
+            Err(Error::Line { number: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn too_many_shapes() {
+        let names = (0..=MAX_SHAPES)
+            .map(|i| format!("shape {i}"))
+            .collect::<Vec<String>>();
+        let game = Game::cyclic(&names.iter().map(String::as_str).collect::<Vec<&str>>()).unwrap();
+        let symbols = "abcdefghijkl".get(..=MAX_SHAPES).unwrap();
+        assert!(matches!(
+            rank_mappings("a b", &game, symbols, symbols),
+            Err(Error::TooManyShapes(9))
+        ));
+    }
+}
@@ -0,0 +1,306 @@
+//! Rock Paper Scissors tournament between strategies.
+//!
+//! Every pair of players plays a match of a fixed number of rounds. Rounds are scored with
+//! [`play`] so the opponent's points, which [`decrypt`](super::decrypt) throws away, are kept.
+
+use std::fmt::Display;
+
+use super::{play, RoundOutcome, Shape};
+
+/// Picks the next shape from the rounds played so far in the current match.
+/// `history` holds `(mine, opponent)` pairs, oldest first.
+pub trait Strategy {
+    fn next_shape(&mut self, history: &[(Shape, Shape)]) -> Shape;
+}
+
+/// Always plays the same shape
+pub struct Fixed(pub Shape);
+
+impl Strategy for Fixed {
+    fn next_shape(&mut self, _history: &[(Shape, Shape)]) -> Shape {
+        self.0.clone()
+    }
+}
+
+/// Plays `shapes` in order and starts over when they run out. Without shapes it plays `Rock`.
+pub struct Cyclic(pub Vec<Shape>);
+
+impl Strategy for Cyclic {
+    fn next_shape(&mut self, history: &[(Shape, Shape)]) -> Shape {
+        if self.0.is_empty() {
+            return Shape::Rock;
+        }
+        self.0[history.len() % self.0.len()].clone()
+    }
+}
+
+/// Plays the shape that beats what the opponent has played the most. Starts with `Rock`.
+pub struct FrequencyCounter;
+
+impl Strategy for FrequencyCounter {
+    fn next_shape(&mut self, history: &[(Shape, Shape)]) -> Shape {
+        if history.is_empty() {
+            return Shape::Rock;
+        }
+        let mut counts = [0; 3];
+        for (_mine, opponent) in history {
+            counts[opponent.index()] += 1;
+        }
+        let most_played = (0..3)
+            .rev()
+            .max_by_key(|&index| counts[index])
+            .and_then(Shape::from_index)
+            .unwrap_or(Shape::Rock);
+        most_played.get_shape(&RoundOutcome::Victory)
+    }
+}
+
+/// Plays uniformly random shapes. The same seed always gives the same shapes.
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        const MIX: u64 = 0x9E37_79B9_7F4A_7C15;
+
+        // xorshift gets stuck on zero, which only the seed `MIX` itself would give
+        let state = seed ^ MIX;
+        Self {
+            state: if state == 0 { MIX } else { state },
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        // xorshift64*
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+impl Strategy for Random {
+    fn next_shape(&mut self, _history: &[(Shape, Shape)]) -> Shape {
+        let index = usize::try_from(self.next_u64() % 3).expect("below three");
+        Shape::from_index(index).unwrap_or(Shape::Rock)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Standing {
+    pub name: String,
+    pub points: i32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Standing {
+    fn record(&mut self, points: i32, outcome: RoundOutcome) {
+        self.points += points;
+        match outcome {
+            RoundOutcome::Loss => self.losses += 1,
+            RoundOutcome::Draw => self.draws += 1,
+            RoundOutcome::Victory => self.wins += 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Round {
+    pub shapes: (Shape, Shape),
+    pub points: (i32, i32),
+    /// Outcome for the second player
+    pub outcome: RoundOutcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    /// Indices of the two players in [`Report::standings`]
+    pub players: (usize, usize),
+    pub rounds: Vec<Round>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// One entry per player in the order they joined the tournament
+    pub standings: Vec<Standing>,
+    pub matches: Vec<Match>,
+}
+
+impl Report {
+    /// Standings sorted by points, then wins
+    #[must_use]
+    pub fn leaderboard(&self) -> Vec<&Standing> {
+        let mut leaderboard = self.standings.iter().collect::<Vec<&Standing>>();
+        leaderboard.sort_by_key(|standing| std::cmp::Reverse((standing.points, standing.wins)));
+        leaderboard
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for standing in self.leaderboard() {
+            writeln!(
+                f,
+                "{:<20} {:>8} {:>6}W {:>6}D {:>6}L",
+                standing.name, standing.points, standing.wins, standing.draws, standing.losses
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct Tournament {
+    players: Vec<(String, Box<dyn Strategy>)>,
+}
+
+impl Tournament {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn player(mut self, name: &str, strategy: Box<dyn Strategy>) -> Self {
+        self.players.push((name.to_string(), strategy));
+        self
+    }
+
+    /// Every player meets every other player once for `rounds` rounds.
+    #[must_use]
+    pub fn run(mut self, rounds: usize) -> Report {
+        let mut standings = self
+            .players
+            .iter()
+            .map(|(name, _)| Standing {
+                name: name.clone(),
+                ..Standing::default()
+            })
+            .collect::<Vec<Standing>>();
+        let mut matches = vec![];
+
+        for first in 0..self.players.len() {
+            for second in first + 1..self.players.len() {
+                let (left, right) = self.players.split_at_mut(second);
+                let first_strategy = &mut left[first].1;
+                let second_strategy = &mut right[0].1;
+
+                let mut first_history = vec![];
+                let mut second_history = vec![];
+                let mut log = vec![];
+                for _ in 0..rounds {
+                    let shapes = (
+                        first_strategy.next_shape(&first_history),
+                        second_strategy.next_shape(&second_history),
+                    );
+                    let outcome = shapes.0.battle(&shapes.1);
+                    let points = play(shapes.clone());
+
+                    standings[first].record(points.0, outcome.reversed());
+                    standings[second].record(points.1, outcome);
+                    first_history.push((shapes.0.clone(), shapes.1.clone()));
+                    second_history.push((shapes.1.clone(), shapes.0.clone()));
+                    log.push(Round {
+                        shapes,
+                        points,
+                        outcome,
+                    });
+                }
+                matches.push(Match {
+                    players: (first, second),
+                    rounds: log,
+                });
+            }
+        }
+
+        Report { standings, matches }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cyclic, Fixed, FrequencyCounter, Random, Strategy, Tournament};
+    use crate::day_2::{RoundOutcome as RO, Shape};
+
+    #[test]
+    fn fixed_against_counter() {
+        let report = Tournament::new()
+            .player("rock", Box::new(Fixed(Shape::Rock)))
+            .player("counter", Box::new(FrequencyCounter))
+            .run(10);
+
+        // counter opens with rock, then plays paper for the rest of the match
+        let rounds = &report.matches[0].rounds;
+        assert_eq!(rounds[0].outcome, RO::Draw);
+        assert!(rounds[1..].iter().all(|round| round.outcome == RO::Victory));
+
+        let [rock, counter] = [&report.standings[0], &report.standings[1]];
+        assert_eq!((rock.wins, rock.draws, rock.losses), (0, 1, 9));
+        assert_eq!((counter.wins, counter.draws, counter.losses), (9, 1, 0));
+        assert_eq!(rock.points, 4 + 9);
+        assert_eq!(counter.points, 4 + 9 * 8);
+        assert_eq!(report.leaderboard()[0].name, "counter");
+    }
+
+    #[test]
+    fn round_robin() {
+        let report = Tournament::new()
+            .player("rock", Box::new(Fixed(Shape::Rock)))
+            .player(
+                "cycle",
+                Box::new(Cyclic(vec![Shape::Rock, Shape::Paper, Shape::Scissors])),
+            )
+            .player("random", Box::new(Random::new(2022)))
+            .run(30);
+
+        assert_eq!(report.matches.len(), 3);
+        for standing in &report.standings {
+            assert_eq!(standing.wins + standing.draws + standing.losses, 60);
+        }
+        let points = report
+            .matches
+            .iter()
+            .flat_map(|game| &game.rounds)
+            .map(|round| round.points.0 + round.points.1)
+            .sum::<i32>();
+        let total = report
+            .standings
+            .iter()
+            .map(|standing| standing.points)
+            .sum::<i32>();
+        assert_eq!(points, total);
+        assert_eq!(report.to_string().lines().count(), 3);
+    }
+
+    #[test]
+    fn random_is_seeded() {
+        let shapes = |seed| {
+            let mut random = Random::new(seed);
+            (0..50)
+                .map(|_| random.next_shape(&[]))
+                .collect::<Vec<Shape>>()
+        };
+        assert_eq!(shapes(7), shapes(7));
+        assert_ne!(shapes(7), shapes(8));
+        assert!([Shape::Rock, Shape::Paper, Shape::Scissors]
+            .iter()
+            .all(|shape| shapes(0).contains(shape)));
+        assert!([Shape::Rock, Shape::Paper, Shape::Scissors]
+            .iter()
+            .all(|shape| shapes(0x9E37_79B9_7F4A_7C15).contains(shape)));
+    }
+
+    #[test]
+    fn empty_cycle() {
+        let history = [(Shape::Paper, Shape::Rock)];
+        assert_eq!(Cyclic(vec![]).next_shape(&history), Shape::Rock);
+        assert_eq!(
+            Cyclic(vec![Shape::Paper, Shape::Scissors]).next_shape(&history),
+            Shape::Scissors
+        );
+    }
+}
@@ -1,4 +1,16 @@
-#[derive(Debug, PartialEq, Eq)]
+use std::fmt::Display;
+
+use crate::parsing::{groups, ParseError};
+
+use self::crane::{Cost, CraneModel, CrateMover9000, CrateMover9001, Statistics};
+
+pub mod crane;
+pub mod drawing;
+pub mod machine;
+pub mod planner;
+pub mod program;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Instruction {
     repetitions: u32,
     src: u8,
@@ -6,235 +18,530 @@ pub struct Instruction {
 }
 
 impl Instruction {
-    fn execute_one_at_a_time(&self, stacks: &mut [Vec<char>]) {
-        for _ in 0..self.repetitions {
-            let block = stacks[self.src as usize - 1].pop().unwrap();
-            stacks[self.dest as usize - 1].push(block);
+    #[must_use]
+    pub fn new(repetitions: u32, src: u8, dest: u8) -> Self {
+        Self {
+            repetitions,
+            src,
+            dest,
         }
     }
-    fn execute_many_at_a_time(&self, stacks: &mut [Vec<char>]) {
-        let remaining_size = stacks[self.src as usize - 1].len() - self.repetitions as usize;
-        let blocks = &stacks[self.src as usize - 1].split_off(remaining_size);
 
-        for block in blocks {
-            stacks[self.dest as usize - 1].push(*block);
+    /// The instruction as `N A->B`
+    #[must_use]
+    pub fn shorthand(&self) -> String {
+        format!("{} {}->{}", self.repetitions, self.src, self.dest)
+    }
+}
+
+/// The instruction as the puzzle writes it, `move N from A to B`
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.repetitions, self.src, self.dest
+        )
+    }
+}
+
+/// Why an instruction can not be executed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reason {
+    /// the stack number is `0` or larger than the number of stacks
+    NoSuchStack(u8),
+    NotEnoughCrates {
+        stack: u8,
+        available: usize,
+        requested: u32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// the drawing above the first blank line can not be read
+    Drawing(drawing::Error),
+    /// a line below the drawing is not an instruction
+    Program(ParseError<program::SyntaxError>),
+    InvalidInstruction {
+        /// 0-based position of the instruction in the program
+        index: usize,
+        instruction: Instruction,
+        /// number of crates on each stack right before the instruction
+        stack_sizes: Vec<usize>,
+        reason: Reason,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Drawing(error) => write!(f, "{error}"),
+            Error::Program(error) => write!(f, "{error}"),
+            Error::InvalidInstruction {
+                index,
+                instruction,
+                stack_sizes,
+                reason,
+            } => {
+                write!(f, "instruction {index} \"{instruction}\" failed: ")?;
+                match reason {
+                    Reason::NoSuchStack(stack) => write!(
+                        f,
+                        "there is no stack {stack}, stacks are numbered 1 to {}",
+                        stack_sizes.len()
+                    ),
+                    Reason::NotEnoughCrates {
+                        stack,
+                        available,
+                        requested,
+                    } => write!(
+                        f,
+                        "stack {stack} holds {available} crates but {requested} were requested"
+                    ),
+                }?;
+                write!(f, " (stack sizes {stack_sizes:?})")
+            }
         }
     }
+}
 
-    fn get_top_stack_as_string(stacks: &Vec<Vec<char>>) -> String {
-        let mut result = "".to_string();
-        for stack in stacks {
-            if let Some(character) = stack.last() {
-                result.push(*character);
-            };
+impl Instruction {
+    /// Checks that the instruction can be executed on `stacks` and returns the indices of the
+    /// source and destination stacks
+    fn validate(&self, stacks: &[Vec<String>]) -> Result<(usize, usize), Reason> {
+        let index = |stack: u8| match stack as usize {
+            0 => Err(Reason::NoSuchStack(stack)),
+            number if number > stacks.len() => Err(Reason::NoSuchStack(stack)),
+            number => Ok(number - 1),
+        };
+        let (src, dest) = (index(self.src)?, index(self.dest)?);
+
+        let available = stacks[src].len();
+        if available < self.repetitions as usize {
+            return Err(Reason::NotEnoughCrates {
+                stack: self.src,
+                available,
+                requested: self.repetitions,
+            });
+        }
+        Ok((src, dest))
+    }
+
+    /// Executes the instruction with `crane` or leaves `stacks` untouched when it can not be
+    /// executed.
+    fn execute(&self, crane: &dyn CraneModel, stacks: &mut [Vec<String>]) -> Result<Cost, Reason> {
+        let (src, dest) = self.validate(stacks)?;
+        Ok(crane.move_crates(stacks, self.repetitions as usize, src, dest))
+    }
+
+    /// [`Instruction::execute`] for the instruction at `index` of a program
+    fn execute_at(
+        &self,
+        index: usize,
+        crane: &dyn CraneModel,
+        stacks: &mut [Vec<String>],
+    ) -> Result<Cost, Error> {
+        self.execute(crane, stacks)
+            .map_err(|reason| self.error_at(index, stacks, reason))
+    }
+
+    /// [`Error::InvalidInstruction`] for the instruction at `index` of a program
+    fn error_at(&self, index: usize, stacks: &[Vec<String>], reason: Reason) -> Error {
+        Error::InvalidInstruction {
+            index,
+            instruction: self.clone(),
+            stack_sizes: stacks.iter().map(Vec::len).collect(),
+            reason,
         }
-        result
+    }
+
+    /// Label of the crate on top of each stack, empty stacks are skipped
+    fn get_top_labels(stacks: &[Vec<String>]) -> Vec<String> {
+        stacks
+            .iter()
+            .filter_map(|stack| stack.last().cloned())
+            .collect()
     }
 }
 
+/// The built-in crane models. Other models implement [`CraneModel`] directly.
 pub enum Crane {
     CrateMover9000,
     CrateMover9001,
 }
 
-/// # Panics
-/// Can panic yes
-#[must_use]
-pub fn simulate_crane(input: &str, crane: &Crane) -> String {
-    let (remaining_input, mut stacks) = parser::parse_crate_setup(input);
-    let (remaining_input, _trash) = parser::parse_trash(remaining_input).unwrap();
-    let instructions = parser::parse_instructions(remaining_input);
-
-    match crane {
-        Crane::CrateMover9000 => {
-            for instruction in instructions {
-                instruction.execute_one_at_a_time(&mut stacks);
-            }
+impl CraneModel for Crane {
+    fn name(&self) -> String {
+        match self {
+            Crane::CrateMover9000 => CrateMover9000.name(),
+            Crane::CrateMover9001 => CrateMover9001.name(),
         }
-        Crane::CrateMover9001 => {
-            for instruction in instructions {
-                instruction.execute_many_at_a_time(&mut stacks);
-            }
+    }
+
+    fn move_crates(
+        &self,
+        stacks: &mut [Vec<String>],
+        count: usize,
+        src: usize,
+        dest: usize,
+    ) -> Cost {
+        match self {
+            Crane::CrateMover9000 => CrateMover9000.move_crates(stacks, count, src, dest),
+            Crane::CrateMover9001 => CrateMover9001.move_crates(stacks, count, src, dest),
         }
     }
+}
 
-    Instruction::get_top_stack_as_string(&stacks)
+/// Splits `input` into the stacks from the drawing and the instructions
+fn parse(input: &str) -> Result<(Vec<Vec<String>>, Vec<Instruction>), Error> {
+    // the drawing ends at the first blank line, everything after it is the program
+    let mut sections = groups(input);
+    let drawing = sections.next().map_or("", |group| group.text);
+    let program = sections.remainder();
+    let first_line = input[..input.len() - program.len()].matches('\n').count() + 1;
+
+    let stacks = parser::parse_crate_setup(drawing).map_err(Error::Drawing)?;
+    let instructions = parser::parse_instructions(program, first_line).map_err(Error::Program)?;
+    Ok((stacks, instructions))
 }
 
-fn transpose_and_reverse(matrix: &[Vec<Option<char>>]) -> Vec<Vec<char>> {
-    let mut transposed: Vec<Vec<char>> = vec![];
-    for col in 0..matrix[0].len() {
-        let mut transposed_row: Vec<char> = vec![];
-        (0..matrix.len()).for_each(|row| {
-            if let Some(c) = matrix[row][col] {
-                transposed_row.push(c);
-            }
+/// Runs `instructions` on `stacks` and calls `after_each` with the stacks after every
+/// instruction. Stops at the first instruction that can not be executed.
+fn run(
+    stacks: &mut [Vec<String>],
+    instructions: &[Instruction],
+    crane: &dyn CraneModel,
+    mut after_each: impl FnMut(&Instruction, &[Vec<String>]),
+) -> Result<Statistics, Error> {
+    let mut statistics = Statistics {
+        model: crane.name(),
+        ..Statistics::default()
+    };
+    for (index, instruction) in instructions.iter().enumerate() {
+        statistics.cost += instruction.execute_at(index, crane, stacks)?;
+        statistics.instructions += 1;
+        after_each(instruction, stacks);
+    }
+    Ok(statistics)
+}
+
+/// Returns the labels of the crates on top of each stack after the crane has executed all
+/// instructions. The puzzle answer is their concatenation.
+///
+/// # Errors
+/// [`Error::Drawing`] or [`Error::Program`] if the drawing or the instructions can not be parsed,
+/// [`Error::InvalidInstruction`] for the first instruction that moves from or to a stack that does
+/// not exist or moves more crates than the stack holds
+pub fn simulate_crane(input: &str, crane: &dyn CraneModel) -> Result<Vec<String>, Error> {
+    simulate(input, crane).map(|simulation| simulation.top())
+}
+
+/// Final stacks of a crane program and what it took to get there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Simulation {
+    pub stacks: Vec<Vec<String>>,
+    pub statistics: Statistics,
+}
+
+impl Simulation {
+    /// Labels of the crates on top of each stack
+    #[must_use]
+    pub fn top(&self) -> Vec<String> {
+        Instruction::get_top_labels(&self.stacks)
+    }
+}
+
+/// Like [`simulate_crane`] but keeps the final stacks and the statistics of the crane.
+///
+/// # Errors
+/// see [`simulate_crane`]
+pub fn simulate(input: &str, crane: &dyn CraneModel) -> Result<Simulation, Error> {
+    let (mut stacks, instructions) = parse(input)?;
+
+    let statistics = run(&mut stacks, &instructions, crane, |_, _| ())?;
+
+    Ok(Simulation { stacks, statistics })
+}
+
+/// Stacks right after `instruction` was executed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub instruction: Instruction,
+    pub stacks: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    /// Stacks from the drawing
+    pub initial: Vec<Vec<String>>,
+    pub steps: Vec<Step>,
+}
+
+impl Trace {
+    /// Index of the first step where the stacks of the two traces differ. Useful to see where two
+    /// crane models part ways on the same program.
+    #[must_use]
+    pub fn first_difference(&self, other: &Self) -> Option<usize> {
+        self.steps
+            .iter()
+            .zip(&other.steps)
+            .position(|(mine, theirs)| mine.stacks != theirs.stacks)
+            .or_else(|| {
+                (self.steps.len() != other.steps.len())
+                    .then(|| self.steps.len().min(other.steps.len()))
+            })
+    }
+}
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", drawing::render(&self.initial))?;
+        for step in &self.steps {
+            writeln!(f)?;
+            writeln!(f, "{}", step.instruction)?;
+            write!(f, "{}", drawing::render(&step.stacks))?;
+        }
+        Ok(())
+    }
+}
+
+/// Like [`simulate_crane`] but keeps the stacks after every instruction.
+///
+/// # Errors
+/// see [`simulate_crane`]
+pub fn trace_crane(input: &str, crane: &dyn CraneModel) -> Result<Trace, Error> {
+    let (initial, instructions) = parse(input)?;
+
+    let mut stacks = initial.clone();
+    let mut steps = vec![];
+    run(&mut stacks, &instructions, crane, |instruction, stacks| {
+        steps.push(Step {
+            instruction: instruction.clone(),
+            stacks: stacks.to_vec(),
         });
+    })?;
+
+    Ok(Trace { initial, steps })
+}
+
+fn transpose_and_reverse<T: Clone>(matrix: &[Vec<Option<T>>]) -> Vec<Vec<T>> {
+    // rows may be shorter than the widest row when trailing spaces are missing
+    let width = matrix.iter().map(Vec::len).max().unwrap_or(0);
+    let mut transposed: Vec<Vec<T>> = vec![];
+    for col in 0..width {
+        let mut transposed_row: Vec<T> = vec![];
+        for row in matrix {
+            if let Some(Some(c)) = row.get(col) {
+                transposed_row.push(c.clone());
+            }
+        }
         transposed.push(transposed_row);
     }
-    transposed.iter_mut().for_each(|row| row.reverse());
+    for row in &mut transposed {
+        row.reverse();
+    }
     transposed
 }
 
 mod parser {
-    use super::Instruction;
-    use crate::day_5::transpose_and_reverse;
+    use std::{ops::Range, str::FromStr};
+
+    use super::{drawing::Error as DrawingError, program::SyntaxError, Instruction};
+    use crate::{day_5::transpose_and_reverse, parsing::ParseError};
     use nom::{
         branch::alt,
-        bytes::complete::{is_not, tag},
-        character::complete::{
-            alpha1, anychar, char, digit1, line_ending, newline, not_line_ending,
-        },
-        combinator::opt,
-        error::Error,
-        sequence::{delimited, tuple},
-        IResult,
+        bytes::complete::tag,
+        character::complete::{alphanumeric1, char, digit1, space0, space1},
+        combinator::{all_consuming, cut, map, map_res, recognize},
+        error::{Error, ErrorKind},
+        multi::many0,
+        sequence::{delimited, preceded, terminated, tuple},
+        IResult, Offset,
     };
 
-    /// consume one cell (three spaces) from input
-    fn crate_cell(input: &str) -> IResult<&str, Option<char>> {
-        let (remainder, consumed) =
-            alt((delimited(char('['), alpha1, char(']')), tag("   ")))(input)?;
-
-        let cell = match consumed {
-            "   " => None,
-            value => {
-                let character = value.chars().next().unwrap();
-                Some(character)
-            }
-        };
-
-        Ok((remainder, cell))
+    /// Tokens of `line` matched by `token` with the range of the line they cover. Tokens may be
+    /// separated by any number of spaces.
+    fn tokens<'a>(
+        line: &'a str,
+        token: impl FnMut(&'a str) -> IResult<&'a str, &'a str>,
+    ) -> IResult<&'a str, Vec<(Range<usize>, &'a str)>> {
+        let (remainder, tokens) =
+            all_consuming(terminated(many0(preceded(space0, token)), space0))(line)?;
+        let tokens = tokens
+            .into_iter()
+            .map(|token| {
+                let start = line.offset(token);
+                (start..start + token.len(), token)
+            })
+            .collect();
+        Ok((remainder, tokens))
     }
 
-    /// Consumes one line from `input` and returns the remaining of input without parsed line
-    /// and a vec of chars
-    fn crate_line(input: &str) -> IResult<&str, Vec<Option<char>>, Error<&str>> {
-        let mut skewed_stack = vec![];
-
-        // get everything before a line ending
-        let (remainder, mut line) = not_line_ending(input)?;
-
-        while !line.is_empty() {
-            if line == " " {
-                break;
-            }
-
-            // parse one cell
-            let cell;
-            (line, cell) = crate_cell(line)?;
-            skewed_stack.push(cell);
+    /// The crates in one row of the drawing, `[AB12]` has the label `AB12`
+    fn crate_line(input: &str) -> IResult<&str, Vec<(Range<usize>, &str)>> {
+        let (remainder, cells) = tokens(
+            input,
+            recognize(delimited(char('['), alphanumeric1, char(']'))),
+        )?;
+        let labels = cells
+            .into_iter()
+            .map(|(range, cell)| (range, &cell[1..cell.len() - 1]))
+            .collect();
+        Ok((remainder, labels))
+    }
 
-            // consume one space if present
-            if !line.is_empty() {
-                let result = anychar(line)?;
-                let (trimmed_line, character) = result;
-                line = if character == ' ' { trimmed_line } else { line };
-            }
-        }
+    /// The stack numbers under the drawing
+    fn number_line(input: &str) -> IResult<&str, Vec<(Range<usize>, &str)>> {
+        tokens(input, digit1)
+    }
 
-        Ok((remainder, skewed_stack))
+    fn number<T: FromStr>(input: &str) -> IResult<&str, T> {
+        map_res(digit1, str::parse)(input)
     }
 
+    /// `move N from A to B` or the shorthand `N A->B`
     fn instruction(input: &str) -> IResult<&str, Instruction, Error<&str>> {
-        // example "move 2 from 2 to 8";
-
-        let (remaining, consumed) = tuple((
-            opt(newline),
-            tag("move "),
-            digit1, // repetitions
-            tag(" from "),
-            digit1, // src
-            tag(" to "),
-            digit1, // dest
-            opt(newline),
-        ))(input)?;
-        let (_, _, repetitions, _, src, _, dest, _) = consumed;
-
-        // todo handle errors. Require converting errors
-        let repetitions = repetitions.parse::<u32>().unwrap();
-        let src = src.parse::<u8>().unwrap();
-        let dest = dest.parse::<u8>().unwrap();
-
-        let instruction = Instruction {
-            repetitions,
-            src,
-            dest,
-        };
-
-        Ok((remaining, instruction))
+        // once the keyword is there the line has to be a long instruction
+        let long = preceded(
+            terminated(tag("move"), space1),
+            cut(tuple((
+                terminated(number, delimited(space1, tag("from"), space1)),
+                terminated(number, delimited(space1, tag("to"), space1)),
+                number,
+            ))),
+        );
+        let short = tuple((
+            terminated(number, space1),
+            terminated(number, delimited(space0, tag("->"), space0)),
+            number,
+        ));
+
+        map(alt((long, short)), |(repetitions, src, dest)| {
+            Instruction::new(repetitions, src, dest)
+        })(input)
     }
 
-    pub fn parse_crate_setup(input: &str) -> (&str, Vec<Vec<char>>) {
-        fn until_wrapper<'a>(input: &'a str, characters: &'a str) -> IResult<&'a str, &'a str> {
-            is_not(characters)(input)
+    /// One line of a program: an instruction, a `#` comment, both or nothing
+    fn instruction_line(line: &str) -> Result<Option<Instruction>, SyntaxError> {
+        let code = line.split_once('#').map_or(line, |(code, _)| code).trim();
+        if code.is_empty() {
+            return Ok(None);
         }
-        // strip off number line
-        let (remainder, creates_section) = until_wrapper(input, "1").unwrap();
-
-        let mut matrix: Vec<Vec<Option<char>>> = vec![];
-        for line in creates_section.lines() {
-            let (_, row) = crate_line(line).unwrap();
-            if !row.is_empty() {
-                matrix.push(row);
+        match all_consuming(instruction)(code) {
+            Ok((_, instruction)) => Ok(Some(instruction)),
+            Err(nom::Err::Error(error) | nom::Err::Failure(error))
+                if error.code == ErrorKind::MapRes =>
+            {
+                Err(SyntaxError::NumberOutOfRange)
             }
+            Err(_) => Err(SyntaxError::Unrecognized),
         }
-        let transposed = transpose_and_reverse(&matrix);
-        (remainder, transposed)
     }
 
-    pub fn parse_trash(input: &str) -> IResult<&str, &str> {
-        let (remainder, _consumed) = not_line_ending(input)?;
-        let (remainder, _consumed) = line_ending(remainder)?;
-        let (remainder, consumed) = line_ending(remainder)?;
-
-        Ok((remainder, consumed))
+    /// Stacks in the drawing, bottom crate first. The last line numbers the stacks from 1, every
+    /// other line is a row of crates.
+    pub fn parse_crate_setup(input: &str) -> Result<Vec<Vec<String>>, DrawingError> {
+        let lines = input.lines().collect::<Vec<&str>>();
+        let Some((numbers, rows)) = lines.split_last() else {
+            return Err(DrawingError::MissingNumberLine);
+        };
+        let (_, numbers) = number_line(numbers).map_err(|_| DrawingError::MissingNumberLine)?;
+        for (index, (_, number)) in numbers.iter().enumerate() {
+            if number.parse() != Ok(index + 1) {
+                return Err(DrawingError::UnexpectedNumber {
+                    expected: index + 1,
+                    found: (*number).to_string(),
+                });
+            }
+        }
+        let stacks = numbers.len();
+
+        // a crate belongs to the only stack whose number it stands above
+        let mut matrix: Vec<Vec<Option<String>>> = vec![];
+        for (index, line) in rows.iter().enumerate() {
+            let (_, cells) = crate_line(line).map_err(|_| DrawingError::InvalidRow {
+                line: index + 1,
+                content: (*line).to_string(),
+            })?;
+            let mut row = vec![None; stacks];
+            for (range, label) in cells {
+                let mut below = numbers.iter().enumerate().filter(|(_, (number, _))| {
+                    number.start < range.end && range.start < number.end
+                });
+                match (below.next(), below.next()) {
+                    (Some((stack, _)), None) if row[stack].is_none() => {
+                        row[stack] = Some(label.to_string());
+                    }
+                    _ => {
+                        return Err(DrawingError::UnalignedCrate {
+                            line: index + 1,
+                            label: label.to_string(),
+                        })
+                    }
+                }
+            }
+            matrix.push(row);
+        }
+        let mut transposed = transpose_and_reverse(&matrix);
+        transposed.resize(stacks, vec![]);
+        Ok(transposed)
     }
 
-    pub fn parse_instructions(input: &str) -> Vec<Instruction> {
+    /// Instructions of `input`, whose first line is line `first_line` of the whole input
+    pub fn parse_instructions(
+        input: &str,
+        first_line: usize,
+    ) -> Result<Vec<Instruction>, ParseError<SyntaxError>> {
         let mut instructions = vec![];
-
-        let mut input = input;
-        while !input.is_empty() {
-            let (remaining, tmp_instruction) = instruction(input).unwrap();
-            instructions.push(tmp_instruction);
-            input = remaining;
+        for (line, content) in (first_line..).zip(input.lines()) {
+            let instruction = instruction_line(content).map_err(|reason| ParseError {
+                line,
+                content: content.to_string(),
+                reason,
+            })?;
+            instructions.extend(instruction);
         }
-        instructions
+        Ok(instructions)
     }
 
     #[cfg(test)]
     mod tests {
-        use super::{parse_crate_setup, parse_trash};
-        use crate::day_5::{
-            parser::{crate_line, instruction},
-            tests::EXAMPLE_INPUT,
-            transpose_and_reverse, Instruction,
+        use super::parse_crate_setup;
+        use crate::{
+            day_5::drawing::Error as DrawingError,
+            day_5::{
+                parser::{crate_line, instruction, instruction_line, parse_instructions},
+                program::SyntaxError,
+                tests::{from_chars, EXAMPLE_INPUT},
+                transpose_and_reverse, Instruction,
+            },
+            parsing::groups,
         };
 
         #[test]
         fn test_create_line() {
-            const INPUT: [&str; 6] = [
+            const INPUT: [&str; 5] = [
                 "[A]",
                 "[A] [B]",
-                "[F] [U] [C] [K]    ",
-                "    [F] [U] [C] [K]",
-                "    [F] [U] [C] [K]    ",
                 "    [F] [U]     [C] [K]    ",
+                "[AB12] [C]",
+                "",
             ];
             let expected = [
-                vec![Some('A')],
-                vec![Some('A'), Some('B')],
-                vec![Some('F'), Some('U'), Some('C'), Some('K'), None],
-                vec![None, Some('F'), Some('U'), Some('C'), Some('K')],
-                vec![None, Some('F'), Some('U'), Some('C'), Some('K'), None],
-                vec![None, Some('F'), Some('U'), None, Some('C'), Some('K'), None],
+                vec![(0..3, "A")],
+                vec![(0..3, "A"), (4..7, "B")],
+                vec![(4..7, "F"), (8..11, "U"), (16..19, "C"), (20..23, "K")],
+                vec![(0..6, "AB12"), (7..10, "C")],
+                vec![],
             ];
 
             for (i, expected_output) in expected.iter().enumerate() {
                 let (_, output) = crate_line(INPUT[i]).unwrap();
                 assert_eq!(&output, expected_output);
             }
+            assert!(crate_line("[A] [B").is_err());
+            assert!(crate_line("[A-B]").is_err());
         }
 
         #[test]
@@ -247,6 +554,41 @@ mod parser {
             assert_eq!(output_instruction.src, 2);
             assert_eq!(output_instruction.dest, 3);
             assert_eq!(remaining_input, "");
+
+            for shorthand in ["1 2->3", "1 2 -> 3", "move  1  from 2   to 3"] {
+                assert_eq!(instruction(shorthand).unwrap().1, output_instruction);
+            }
+        }
+
+        #[test]
+        fn parse_instruction_line() {
+            let expected = Ok(Some(Instruction::new(12, 1, 9)));
+            assert_eq!(instruction_line("move 12 from 1 to 9"), expected);
+            assert_eq!(instruction_line("  12 1->9  # twelve crates"), expected);
+            assert_eq!(instruction_line("# only a comment"), Ok(None));
+            assert_eq!(instruction_line("   "), Ok(None));
+
+            for invalid in [
+                "move 1 from 2",
+                "move 1 from 2 to 3 4",
+                "1 2 3",
+                "move x from 1 to 2",
+            ] {
+                assert_eq!(instruction_line(invalid), Err(SyntaxError::Unrecognized));
+            }
+            assert_eq!(
+                instruction_line("move 1 from 256 to 1"),
+                Err(SyntaxError::NumberOutOfRange)
+            );
+            assert_eq!(
+                instruction_line("1 1->300"),
+                Err(SyntaxError::NumberOutOfRange)
+            );
+
+            let error =
+                parse_instructions("move 1 from 1 to 2\n\n# comment\nmove one", 7).unwrap_err();
+            assert_eq!(error.line, 10);
+            assert_eq!(error.content, "move one");
         }
 
         #[test]
@@ -264,22 +606,64 @@ mod parser {
 
         #[test]
         fn parse_crates() {
-            let expected_matrix = vec![vec!['Z', 'N'], vec!['M', 'C', 'D'], vec!['P']];
-            let (_, matrix) = parse_crate_setup(EXAMPLE_INPUT);
-            assert_eq!(expected_matrix, matrix);
+            let expected_matrix = from_chars(&["ZN", "MCD", "P"]);
+            let drawing = groups(EXAMPLE_INPUT).next().unwrap().text;
+            assert_eq!(Ok(expected_matrix), parse_crate_setup(drawing));
+        }
+
+        #[test]
+        fn structural_drawing() {
+            // labels may be digits, stacks may be empty and there may be more than nine
+            let drawing = format!(
+                "[1] {}[Z]\n[2] [3] {}[9]\n{}",
+                "    ".repeat(9),
+                "    ".repeat(8),
+                " 1   2   3   4   5   6   7   8   9   10  11 ",
+            );
+            let stacks = parse_crate_setup(&drawing).unwrap();
+            assert_eq!(stacks.len(), 11);
+            assert_eq!(stacks[0].concat(), "21");
+            assert_eq!(stacks[2].concat(), "");
+            assert_eq!(stacks[10].concat(), "9Z");
+
+            assert_eq!(
+                parse_crate_setup("[A] [B]"),
+                Err(DrawingError::MissingNumberLine)
+            );
+            assert_eq!(
+                parse_crate_setup("[A] [B]\n 1   3 "),
+                Err(DrawingError::UnexpectedNumber {
+                    expected: 2,
+                    found: "3".to_string()
+                })
+            );
+            assert_eq!(
+                parse_crate_setup("[A] [B] [C]\n 1   2 "),
+                Err(DrawingError::UnalignedCrate {
+                    line: 1,
+                    label: "C".to_string()
+                })
+            );
+            assert_eq!(
+                parse_crate_setup("[A][B]\n  1"),
+                Err(DrawingError::UnalignedCrate {
+                    line: 1,
+                    label: "B".to_string()
+                })
+            );
+            assert_eq!(
+                parse_crate_setup("[A] (B)\n 1   2 "),
+                Err(DrawingError::InvalidRow {
+                    line: 1,
+                    content: "[A] (B)".to_string()
+                })
+            );
         }
 
         #[test]
         fn test_instruction_parser() {
-            let (remainder_and_trash, _matrix) = parse_crate_setup(EXAMPLE_INPUT);
-            let (mut remainder, _trash) = parse_trash(remainder_and_trash).unwrap();
-            let mut instructions = vec![];
-
-            while !remainder.is_empty() {
-                let (tmp_remainder, tmp_instruction) = instruction(remainder).unwrap();
-                instructions.push(tmp_instruction);
-                remainder = tmp_remainder;
-            }
+            let program = groups(EXAMPLE_INPUT).nth(1).unwrap().text;
+            let instructions = parse_instructions(program, 1).unwrap();
 
             let expected_instructions = vec![
                 Instruction {
@@ -311,7 +695,11 @@ mod parser {
 
 #[cfg(test)]
 mod tests {
-    use crate::day_5::{simulate_crane, Crane};
+    use crate::day_5::{
+        crane::{Cost, CraneModel, LimitedLift},
+        drawing::render,
+        simulate, simulate_crane, trace_crane, Crane, Error, Instruction, Reason,
+    };
 
     const INPUT: &str = include_str!("../puzzle_input/day_5.txt");
     pub const EXAMPLE_INPUT: &str = r"    [D]    
@@ -325,16 +713,28 @@ move 2 from 2 to 1
 move 1 from 1 to 2";
 
     const ANSWER: [&str; 2] = ["ZRLJGSCTR", "PRTTGRFPB"];
+
+    /// Stacks with a one character label per crate, `"ZN"` is a stack with `Z` at the bottom
+    pub fn from_chars(stacks: &[&str]) -> Vec<Vec<String>> {
+        stacks
+            .iter()
+            .map(|stack| stack.chars().map(String::from).collect())
+            .collect()
+    }
     const EXAMPLE_ANSWER: [&str; 2] = ["CMZ", "MCD"];
 
     #[test]
     fn example() {
         assert_eq!(
-            simulate_crane(EXAMPLE_INPUT, &Crane::CrateMover9000),
+            simulate_crane(EXAMPLE_INPUT, &Crane::CrateMover9000)
+                .unwrap()
+                .concat(),
             EXAMPLE_ANSWER[0]
         );
         assert_eq!(
-            simulate_crane(EXAMPLE_INPUT, &Crane::CrateMover9001),
+            simulate_crane(EXAMPLE_INPUT, &Crane::CrateMover9001)
+                .unwrap()
+                .concat(),
             EXAMPLE_ANSWER[1]
         );
     }
@@ -342,12 +742,171 @@ move 1 from 1 to 2";
     #[test]
     fn problem() {
         assert_eq!(
-            simulate_crane(INPUT, &Crane::CrateMover9000),
+            simulate_crane(INPUT, &Crane::CrateMover9000)
+                .unwrap()
+                .concat(),
             ANSWER[0].to_string()
         );
         assert_eq!(
-            simulate_crane(INPUT, &Crane::CrateMover9001),
+            simulate_crane(INPUT, &Crane::CrateMover9001)
+                .unwrap()
+                .concat(),
             ANSWER[1].to_string()
         );
     }
+
+    #[test]
+    fn trace() {
+        let trace = trace_crane(EXAMPLE_INPUT, &Crane::CrateMover9000).unwrap();
+        assert_eq!(trace.steps.len(), 4);
+        assert_eq!(trace.initial, from_chars(&["ZN", "MCD", "P"]));
+        assert_eq!(trace.steps[0].stacks, from_chars(&["ZND", "MC", "P"]));
+        assert_eq!(
+            render(&trace.steps[0].stacks),
+            "[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n"
+        );
+        assert!(trace.to_string().starts_with(&render(&trace.initial)));
+        assert!(trace
+            .to_string()
+            .contains("\nmove 1 from 2 to 1\n[D]        \n"));
+
+        let other = trace_crane(EXAMPLE_INPUT, &Crane::CrateMover9001).unwrap();
+        // moving a single crate is the same for both models
+        assert_eq!(trace.first_difference(&other), Some(1));
+        assert_eq!(trace.first_difference(&trace), None);
+    }
+
+    #[test]
+    fn invalid_instructions() {
+        let program = |instructions: &str| format!("[A]    \n[B] [C]\n 1   2 \n\n{instructions}");
+
+        for crane in [Crane::CrateMover9000, Crane::CrateMover9001] {
+            let error = simulate_crane(&program("move 1 from 1 to 2\nmove 3 from 1 to 2"), &crane);
+            assert_eq!(
+                error,
+                Err(Error::InvalidInstruction {
+                    index: 1,
+                    instruction: Instruction {
+                        repetitions: 3,
+                        src: 1,
+                        dest: 2,
+                    },
+                    stack_sizes: vec![1, 2],
+                    reason: Reason::NotEnoughCrates {
+                        stack: 1,
+                        available: 1,
+                        requested: 3,
+                    },
+                })
+            );
+
+            for (instructions, stack) in [
+                ("move 1 from 0 to 2", 0),
+                ("move 1 from 1 to 0", 0),
+                ("move 1 from 3 to 1", 3),
+                ("move 1 from 1 to 9", 9),
+            ] {
+                assert!(matches!(
+                    simulate_crane(&program(instructions), &crane),
+                    Err(Error::InvalidInstruction {
+                        index: 0,
+                        reason: Reason::NoSuchStack(number),
+                        ..
+                    }) if number == stack
+                ));
+            }
+        }
+
+        let error = trace_crane(
+            &program("move 1 from 2 to 1\nmove 4 from 1 to 2"),
+            &Crane::CrateMover9001,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "instruction 1 \"move 4 from 1 to 2\" failed: stack 1 holds 3 crates but 4 were \
+             requested (stack sizes [3, 0])"
+        );
+    }
+
+    /// Drops whatever it lifts onto the destination upside down, one lift per instruction
+    struct Clumsy;
+
+    impl CraneModel for Clumsy {
+        fn name(&self) -> String {
+            "clumsy".to_string()
+        }
+
+        fn move_crates(
+            &self,
+            stacks: &mut [Vec<String>],
+            count: usize,
+            src: usize,
+            dest: usize,
+        ) -> Cost {
+            Crane::CrateMover9000.move_crates(stacks, count, src, dest);
+            Cost {
+                lifts: 1,
+                crates: count as u64,
+                energy: 0,
+            }
+        }
+    }
+
+    #[test]
+    fn crane_models() {
+        let one = simulate(EXAMPLE_INPUT, &Crane::CrateMover9000).unwrap();
+        assert_eq!(one.top(), ["C", "M", "Z"]);
+        assert_eq!(one.statistics.model, "CrateMover 9000");
+        assert_eq!(one.statistics.instructions, 4);
+        assert_eq!(
+            one.statistics.cost,
+            Cost {
+                lifts: 7,
+                crates: 7,
+                energy: 1 + 3 * 2 + 2 + 1
+            }
+        );
+
+        let many = simulate(EXAMPLE_INPUT, &Crane::CrateMover9001).unwrap();
+        assert_eq!(many.top().concat(), EXAMPLE_ANSWER[1]);
+        assert_eq!(many.statistics.cost.lifts, 4);
+        assert_eq!(many.statistics.cost.energy, one.statistics.cost.energy);
+
+        // a lift of one crate is a CrateMover 9000, an unlimited one a CrateMover 9001
+        for (capacity, answer) in [(1, EXAMPLE_ANSWER[0]), (3, EXAMPLE_ANSWER[1])] {
+            let model = LimitedLift {
+                capacity,
+                reverse: false,
+            };
+            assert_eq!(
+                simulate_crane(EXAMPLE_INPUT, &model).unwrap().concat(),
+                answer
+            );
+        }
+        let split = LimitedLift {
+            capacity: 2,
+            reverse: false,
+        };
+        assert_eq!(
+            simulate(INPUT, &split).unwrap().statistics.instructions,
+            502
+        );
+
+        let clumsy = simulate(EXAMPLE_INPUT, &Clumsy).unwrap();
+        assert_eq!(clumsy.stacks, one.stacks);
+        assert_eq!(clumsy.statistics.cost.lifts, 4);
+    }
+
+    #[test]
+    fn wide_labels() {
+        let input =
+            "[AB12]\n[7]    [C]  [D]\n  1     2    3\n\nmove 2 from 1 to 3\nmove 1 from 2 to 1";
+        let simulation = simulate(input, &Crane::CrateMover9000).unwrap();
+        assert_eq!(simulation.top(), ["C", "7"]);
+        assert_eq!(
+            render(&simulation.stacks),
+            "        [7]   \n        [AB12]\n[C]     [D]   \n 1   2   3    \n"
+        );
+    }
 }
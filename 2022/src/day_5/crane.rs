@@ -0,0 +1,208 @@
+//! Crane models.
+//!
+//! A [`CraneModel`] decides how crates travel between two stacks. Instructions are validated
+//! before they reach the model, so a model only has to move crates and report what it cost.
+
+use std::ops::{AddAssign, SubAssign};
+
+/// What moving crates cost a crane
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Cost {
+    /// number of times the crane picked crates up
+    pub lifts: u64,
+    pub crates: u64,
+    /// crates lifted times the number of stacks they travelled, summed over all lifts
+    pub energy: u64,
+}
+
+impl AddAssign for Cost {
+    fn add_assign(&mut self, other: Self) {
+        self.lifts += other.lifts;
+        self.crates += other.crates;
+        self.energy += other.energy;
+    }
+}
+
+impl SubAssign for Cost {
+    fn sub_assign(&mut self, other: Self) {
+        self.lifts -= other.lifts;
+        self.crates -= other.crates;
+        self.energy -= other.energy;
+    }
+}
+
+/// Totals over a whole crane program
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Statistics {
+    pub model: String,
+    pub instructions: usize,
+    pub cost: Cost,
+}
+
+pub trait CraneModel {
+    fn name(&self) -> String;
+
+    /// Moves the top `count` crates of `stacks[src]` onto `stacks[dest]`. Both indices are valid
+    /// and the source stack holds at least `count` crates.
+    fn move_crates(
+        &self,
+        stacks: &mut [Vec<String>],
+        count: usize,
+        src: usize,
+        dest: usize,
+    ) -> Cost;
+}
+
+/// Moves `count` crates in one lift, keeping or reversing their order
+fn lift(stacks: &mut [Vec<String>], count: usize, src: usize, dest: usize, reverse: bool) -> Cost {
+    let remaining_size = stacks[src].len() - count;
+    let mut blocks = stacks[src].split_off(remaining_size);
+    if reverse {
+        blocks.reverse();
+    }
+    stacks[dest].extend(blocks);
+
+    let count = count as u64;
+    Cost {
+        lifts: u64::from(count > 0),
+        crates: count,
+        energy: count * src.abs_diff(dest) as u64,
+    }
+}
+
+/// Moves one crate at a time, which reverses the order of the moved crates
+pub struct CrateMover9000;
+
+impl CraneModel for CrateMover9000 {
+    fn name(&self) -> String {
+        "CrateMover 9000".to_string()
+    }
+
+    fn move_crates(
+        &self,
+        stacks: &mut [Vec<String>],
+        count: usize,
+        src: usize,
+        dest: usize,
+    ) -> Cost {
+        let mut cost = Cost::default();
+        for _ in 0..count {
+            cost += lift(stacks, 1, src, dest, false);
+        }
+        cost
+    }
+}
+
+/// Moves all crates at once and keeps their order
+pub struct CrateMover9001;
+
+impl CraneModel for CrateMover9001 {
+    fn name(&self) -> String {
+        "CrateMover 9001".to_string()
+    }
+
+    fn move_crates(
+        &self,
+        stacks: &mut [Vec<String>],
+        count: usize,
+        src: usize,
+        dest: usize,
+    ) -> Cost {
+        lift(stacks, count, src, dest, false)
+    }
+}
+
+/// Lifts at most `capacity` crates at a time and splits larger moves into several lifts, top
+/// crates first. Each lift keeps the order of its crates unless `reverse` is set.
+pub struct LimitedLift {
+    pub capacity: usize,
+    pub reverse: bool,
+}
+
+impl CraneModel for LimitedLift {
+    fn name(&self) -> String {
+        let order = if self.reverse { "reversing" } else { "ordered" };
+        format!("{order} lift of {}", self.capacity)
+    }
+
+    fn move_crates(
+        &self,
+        stacks: &mut [Vec<String>],
+        count: usize,
+        src: usize,
+        dest: usize,
+    ) -> Cost {
+        let capacity = self.capacity.max(1);
+        let mut cost = Cost::default();
+        let mut remaining = count;
+        while remaining > 0 {
+            let chunk = remaining.min(capacity);
+            cost += lift(stacks, chunk, src, dest, self.reverse);
+            remaining -= chunk;
+        }
+        cost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cost, CraneModel, CrateMover9000, CrateMover9001, LimitedLift};
+    use crate::day_5::tests::from_chars;
+
+    fn stacks() -> Vec<Vec<String>> {
+        from_chars(&["ABCDE", "", "Z"])
+    }
+
+    #[test]
+    fn built_ins() {
+        let mut one = stacks();
+        let cost = CrateMover9000.move_crates(&mut one, 3, 0, 2);
+        assert_eq!(one[2].concat(), "ZEDC");
+        assert_eq!(
+            cost,
+            Cost {
+                lifts: 3,
+                crates: 3,
+                energy: 6
+            }
+        );
+
+        let mut many = stacks();
+        let cost = CrateMover9001.move_crates(&mut many, 3, 0, 2);
+        assert_eq!(many[2].concat(), "ZCDE");
+        assert_eq!(cost.lifts, 1);
+        assert_eq!(cost.energy, 6);
+    }
+
+    #[test]
+    fn limited_lift() {
+        let mut output = stacks();
+        let model = LimitedLift {
+            capacity: 2,
+            reverse: false,
+        };
+        let cost = model.move_crates(&mut output, 5, 0, 1);
+        // [D E] first, then [B C], then [A]
+        assert_eq!(output[1].concat(), "DEBCA");
+        assert_eq!(cost.lifts, 3);
+        assert_eq!(model.name(), "ordered lift of 2");
+
+        let mut output = stacks();
+        let model = LimitedLift {
+            capacity: 1,
+            reverse: false,
+        };
+        let mut expected = stacks();
+        model.move_crates(&mut output, 4, 0, 1);
+        CrateMover9000.move_crates(&mut expected, 4, 0, 1);
+        assert_eq!(output, expected);
+
+        let mut output = stacks();
+        let model = LimitedLift {
+            capacity: 5,
+            reverse: true,
+        };
+        model.move_crates(&mut output, 5, 0, 1);
+        assert_eq!(output[1].concat(), "EDCBA");
+    }
+}
@@ -0,0 +1,163 @@
+//! Stacks drawn the way the puzzle input draws them.
+//!
+//! [`render`] and [`parse`] are inverses: a drawing made by [`render`] reads back into the same
+//! stacks and drawing those stacks again gives the same text, padding included.
+//!
+//! Labels may be longer than one character. Every stack is as wide as its widest crate and a crate
+//! belongs to the stack whose number it stands above.
+
+use std::fmt::Display;
+
+use super::parser::parse_crate_setup;
+
+/// Why a drawing can not be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// the last line does not number the stacks
+    MissingNumberLine,
+    /// stacks have to be numbered 1, 2, 3, ...
+    UnexpectedNumber { expected: usize, found: String },
+    /// 1-based line that is not made of `[X]` cells and spaces
+    InvalidRow { line: usize, content: String },
+    /// a crate does not stand above exactly one stack number or shares it with another crate
+    UnalignedCrate { line: usize, label: String },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::MissingNumberLine => write!(f, "the drawing does not end with stack numbers"),
+            Error::UnexpectedNumber { expected, found } => {
+                write!(f, "expected stack number {expected} but found {found:?}")
+            }
+            Error::InvalidRow { line, content } => {
+                write!(f, "line {line}: {content:?} is not a row of crates")
+            }
+            Error::UnalignedCrate { line, label } => write!(
+                f,
+                "line {line}: crate [{label}] does not stand above a stack of its own"
+            ),
+        }
+    }
+}
+
+/// Draws `stacks` as rows of `[X]` cells from the highest crate down, followed by the line with
+/// the stack numbers. Every line has the full width of the drawing and ends with a new line.
+#[must_use]
+pub fn render(stacks: &[Vec<String>]) -> String {
+    let height = stacks.iter().map(Vec::len).max().unwrap_or(0);
+    // wide enough for the longest label in brackets and for the number after a space
+    let widths = stacks
+        .iter()
+        .enumerate()
+        .map(|(index, stack)| {
+            let label = stack.iter().map(|label| label.chars().count()).max();
+            let number = (index + 1).to_string().len();
+            (label.unwrap_or(0) + 2).max(number + 1).max(3)
+        })
+        .collect::<Vec<usize>>();
+    let mut output = String::new();
+
+    for row in (0..height).rev() {
+        let cells = stacks
+            .iter()
+            .zip(&widths)
+            .map(|(stack, &width)| match stack.get(row) {
+                Some(label) => format!("{:<width$}", format!("[{label}]")),
+                None => " ".repeat(width),
+            })
+            .collect::<Vec<String>>();
+        output.push_str(&cells.join(" "));
+        output.push('\n');
+    }
+
+    let numbers = widths
+        .iter()
+        .enumerate()
+        .map(|(index, width)| format!(" {:<1$}", index + 1, width - 1))
+        .collect::<Vec<String>>();
+    output.push_str(&numbers.join(" "));
+    output.push('\n');
+    output
+}
+
+/// Stacks in the drawing, bottom crate first, one for every number on the last line.
+///
+/// # Errors
+/// if the last line does not number the stacks from 1 or another line is not a row of crates
+/// within the numbered stacks
+pub fn parse(drawing: &str) -> Result<Vec<Vec<String>>, Error> {
+    parse_crate_setup(drawing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, render, Error};
+    use crate::day_5::tests::from_chars;
+    use proptest::prelude::*;
+
+    /// Up to twelve stacks of up to eight crates, empty stacks included
+    fn stacks() -> impl Strategy<Value = Vec<Vec<String>>> {
+        prop::collection::vec(prop::collection::vec("[A-Z0-9]{1,5}", 0..8), 0..12)
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(stacks in stacks()) {
+            let drawing = render(&stacks);
+            prop_assert_eq!(&parse(&drawing).unwrap(), &stacks);
+            prop_assert_eq!(render(&parse(&drawing).unwrap()), drawing);
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let stacks = from_chars(&["ZN", "MCD", "P"]);
+        assert_eq!(
+            render(&stacks),
+            "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n"
+        );
+        assert_eq!(render(&from_chars(&["", "A"])), "    [A]\n 1   2 \n");
+        assert_eq!(render(&[]), "\n");
+
+        let wide = vec![
+            vec!["AB12".to_string(), "X".to_string()],
+            vec!["C".to_string()],
+        ];
+        assert_eq!(render(&wide), "[X]       \n[AB12] [C]\n 1      2 \n");
+    }
+
+    #[test]
+    fn test_parse() {
+        let drawing = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n";
+        assert_eq!(parse(drawing), Ok(from_chars(&["ZN", "MCD", "P"])));
+        assert_eq!(render(&parse(drawing).unwrap()), drawing);
+
+        // trailing spaces are optional when reading
+        let trimmed = "    [D]\n[N] [C]\n[Z] [M] [P]\n 1   2   3";
+        assert_eq!(parse(trimmed), parse(drawing));
+
+        // empty stacks on the right are kept
+        let empty = "[A]        \n 1   2   3 \n";
+        assert_eq!(parse(empty), Ok(from_chars(&["A", "", ""])));
+        assert_eq!(render(&parse(empty).unwrap()), empty);
+
+        // columns follow the numbers, not a fixed width
+        let wide = "[AB12]\n[7]   [C]   [LONGER]\n  1     2       3";
+        assert_eq!(
+            parse(wide),
+            Ok(vec![
+                vec!["7".to_string(), "AB12".to_string()],
+                vec!["C".to_string()],
+                vec!["LONGER".to_string()],
+            ])
+        );
+        assert_eq!(
+            parse("[ABCDEFG]\n 1   2"),
+            Err(Error::UnalignedCrate {
+                line: 1,
+                label: "ABCDEFG".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Stepping through a crane program in both directions.
+//!
+//! Every executed instruction leaves an entry in an undo log with the crates it took from the
+//! source stack, in their original order. Stepping back puts those crates back, so going back and
+//! forth costs as much as the crates moved on the way and never copies the stacks.
+
+use super::{
+    crane::{Cost, CraneModel, Statistics},
+    parse, Error, Instruction,
+};
+
+/// What is needed to take back one instruction
+#[derive(Debug, Clone)]
+struct Undo {
+    src: usize,
+    dest: usize,
+    /// top crates of the source stack before the instruction, bottom first
+    crates: Vec<String>,
+    cost: Cost,
+}
+
+pub struct CraneMachine<'a> {
+    crane: &'a dyn CraneModel,
+    stacks: Vec<Vec<String>>,
+    instructions: Vec<Instruction>,
+    log: Vec<Undo>,
+    statistics: Statistics,
+}
+
+impl<'a> CraneMachine<'a> {
+    /// Loads the drawing and the instructions of `input` without executing anything.
+    ///
+    /// # Errors
+    /// [`Error::Drawing`] or [`Error::Program`] if the drawing or the instructions can not be
+    /// parsed
+    pub fn new(input: &str, crane: &'a dyn CraneModel) -> Result<Self, Error> {
+        let (stacks, instructions) = parse(input)?;
+        Ok(Self {
+            crane,
+            stacks,
+            instructions,
+            log: vec![],
+            statistics: Statistics {
+                model: crane.name(),
+                ..Statistics::default()
+            },
+        })
+    }
+
+    /// Stacks after the instructions executed so far
+    #[must_use]
+    pub fn stacks(&self) -> &[Vec<String>] {
+        &self.stacks
+    }
+
+    #[must_use]
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// Number of instructions executed so far, which is also the index of the next one
+    #[must_use]
+    pub fn position(&self) -> usize {
+        self.log.len()
+    }
+
+    /// Statistics of the instructions executed so far
+    #[must_use]
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.position() == self.instructions.len()
+    }
+
+    /// Executes the next instruction. Returns `false` when the program has already ended.
+    ///
+    /// # Errors
+    /// [`Error::InvalidInstruction`] when the next instruction can not be executed. The machine
+    /// stays where it was.
+    pub fn step(&mut self) -> Result<bool, Error> {
+        let index = self.position();
+        let Some(instruction) = self.instructions.get(index) else {
+            return Ok(false);
+        };
+
+        let (src, dest) = instruction
+            .validate(&self.stacks)
+            .map_err(|reason| instruction.error_at(index, &self.stacks, reason))?;
+        let count = instruction.repetitions as usize;
+        let crates = self.stacks[src][self.stacks[src].len() - count..].to_vec();
+        let cost = self.crane.move_crates(&mut self.stacks, count, src, dest);
+        self.log.push(Undo {
+            src,
+            dest,
+            crates,
+            cost,
+        });
+        self.statistics.instructions += 1;
+        self.statistics.cost += cost;
+        Ok(true)
+    }
+
+    /// Takes back the last executed instruction. Returns `false` at the start of the program.
+    pub fn step_back(&mut self) -> bool {
+        let Some(undo) = self.log.pop() else {
+            return false;
+        };
+        let dest = &mut self.stacks[undo.dest];
+        dest.truncate(dest.len() - undo.crates.len());
+        self.stacks[undo.src].extend(undo.crates);
+        self.statistics.instructions -= 1;
+        self.statistics.cost -= undo.cost;
+        true
+    }
+
+    /// Moves to the state after the first `position` instructions. Positions past the end of the
+    /// program go to the end.
+    ///
+    /// # Errors
+    /// see [`CraneMachine::step`]. The machine stops in front of the failing instruction.
+    pub fn seek(&mut self, position: usize) -> Result<(), Error> {
+        let position = position.min(self.instructions.len());
+        while self.position() > position {
+            self.step_back();
+        }
+        while self.position() < position {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Executes all remaining instructions.
+    ///
+    /// # Errors
+    /// see [`CraneMachine::seek`]
+    pub fn run_to_end(&mut self) -> Result<(), Error> {
+        self.seek(self.instructions.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CraneMachine;
+    use crate::day_5::{
+        simulate,
+        tests::{from_chars, EXAMPLE_INPUT},
+        trace_crane, Crane, Error, Reason,
+    };
+    use crate::parsing::ParseError;
+
+    #[test]
+    fn example() {
+        let trace = trace_crane(EXAMPLE_INPUT, &Crane::CrateMover9000).unwrap();
+        let mut machine = CraneMachine::new(EXAMPLE_INPUT, &Crane::CrateMover9000).unwrap();
+        assert_eq!(machine.stacks(), trace.initial);
+
+        assert!(machine.step().unwrap());
+        assert_eq!(machine.stacks(), trace.steps[0].stacks);
+        assert!(machine.step_back());
+        assert!(!machine.step_back());
+        assert_eq!(machine.stacks(), trace.initial);
+
+        machine.run_to_end().unwrap();
+        assert!(machine.is_finished());
+        assert!(!machine.step().unwrap());
+        assert_eq!(
+            machine.statistics(),
+            &simulate(EXAMPLE_INPUT, &Crane::CrateMover9000)
+                .unwrap()
+                .statistics
+        );
+
+        for position in [2, 0, 3, 1, 4] {
+            machine.seek(position).unwrap();
+            assert_eq!(machine.position(), position);
+            let expected = match position {
+                0 => &trace.initial,
+                _ => &trace.steps[position - 1].stacks,
+            };
+            assert_eq!(machine.stacks(), expected);
+        }
+        machine.seek(100).unwrap();
+        assert_eq!(machine.position(), 4);
+    }
+
+    #[test]
+    fn invalid_instruction() {
+        let input = "[A]    \n[B] [C]\n 1   2 \n\nmove 1 from 2 to 1\nmove 4 from 1 to 2";
+        let mut machine = CraneMachine::new(input, &Crane::CrateMover9001).unwrap();
+        let error = machine.run_to_end().unwrap_err();
+        assert!(matches!(
+            error,
+            Error::InvalidInstruction {
+                index: 1,
+                reason: Reason::NotEnoughCrates { .. },
+                ..
+            }
+        ));
+        assert_eq!(machine.position(), 1);
+        assert!(machine.step_back());
+        assert_eq!(machine.stacks(), from_chars(&["BA", "C"]));
+    }
+
+    #[test]
+    fn unparsable_input() {
+        let crane = Crane::CrateMover9000;
+        assert!(matches!(
+            CraneMachine::new("[A]\n\nmove 1 from 1 to 2", &crane),
+            Err(Error::Drawing(_))
+        ));
+        assert!(matches!(
+            CraneMachine::new("[A]\n 1 \n\nmove 1 from 1 to 2\nlift 1", &crane),
+            Err(Error::Program(ParseError { line: 5, .. }))
+        ));
+    }
+
+    #[test]
+    fn long_program() {
+        let instructions = (0..100_000)
+            .map(|index| {
+                let (src, dest) = [(1, 2), (2, 3), (3, 1)][index % 3];
+                format!("move 2 from {src} to {dest}")
+            })
+            .collect::<Vec<String>>();
+        let input = format!(
+            "[A]        \n[B] [C] [D]\n 1   2   3 \n\n{}",
+            instructions.join("\n")
+        );
+
+        for crane in [Crane::CrateMover9000, Crane::CrateMover9001] {
+            let mut machine = CraneMachine::new(&input, &crane).unwrap();
+            machine.run_to_end().unwrap();
+            assert_eq!(machine.stacks(), simulate(&input, &crane).unwrap().stacks);
+
+            machine.seek(0).unwrap();
+            assert_eq!(machine.stacks(), from_chars(&["BA", "C", "D"]));
+            assert_eq!(machine.statistics().cost.lifts, 0);
+        }
+    }
+}
@@ -0,0 +1,295 @@
+//! Finding crane programs.
+//!
+//! [`plan`] searches for the shortest list of instructions that turns one arrangement of stacks
+//! into another with a given crane model. The search is A* over arrangements. Every instruction
+//! takes crates from one stack and puts them on one other stack, so the number of stacks that
+//! still have to give crates away, or the number that still have to receive some, whichever is
+//! larger, never overestimates the instructions left.
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fmt::Display,
+};
+
+use super::{crane::CraneModel, drawing, Instruction};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    DifferentStackCount {
+        start: usize,
+        target: usize,
+    },
+    /// the target does not hold exactly the crates of the start
+    DifferentCrates,
+    /// instructions can only name stacks up to 255
+    TooManyStacks(usize),
+    /// no program found after looking at this many arrangements
+    SearchLimit(usize),
+    /// the start or the target drawing can not be parsed
+    Drawing(drawing::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::DifferentStackCount { start, target } => write!(
+                f,
+                "the start has {start} stacks but the target has {target}"
+            ),
+            Error::DifferentCrates => write!(f, "the start and the target hold different crates"),
+            Error::TooManyStacks(stacks) => {
+                write!(f, "{stacks} stacks can not be numbered in instructions")
+            }
+            Error::SearchLimit(explored) => {
+                write!(
+                    f,
+                    "no program found after exploring {explored} arrangements"
+                )
+            }
+            Error::Drawing(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+/// Instructions that turn `start` into the target they were planned for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    pub start: Vec<Vec<String>>,
+    pub instructions: Vec<Instruction>,
+}
+
+impl Plan {
+    /// The drawing of the start followed by the instructions, ready for
+    /// [`simulate_crane`](super::simulate_crane).
+    #[must_use]
+    pub fn program(&self) -> String {
+        format!("{}\n{self}", drawing::render(&self.start))
+    }
+}
+
+/// One instruction per line in the puzzle syntax
+impl Display for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for instruction in &self.instructions {
+            writeln!(f, "{instruction}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Lower bound of the instructions needed to turn `stacks` into `target`
+fn estimate(stacks: &[Vec<String>], target: &[Vec<String>]) -> usize {
+    let mut give = 0;
+    let mut receive = 0;
+    for (stack, wanted) in stacks.iter().zip(target) {
+        let common = stack
+            .iter()
+            .zip(wanted)
+            .take_while(|(current, wanted)| current == wanted)
+            .count();
+        give += usize::from(stack.len() > common);
+        receive += usize::from(wanted.len() > common);
+    }
+    give.max(receive)
+}
+
+/// Searches for the shortest program that turns `start` into `target` with `crane`, looking at
+/// no more than `limit` arrangements.
+///
+/// # Errors
+/// when the start and the target can not be matched or no program is found within `limit`
+pub fn plan(
+    start: &[Vec<String>],
+    target: &[Vec<String>],
+    crane: &dyn CraneModel,
+    limit: usize,
+) -> Result<Plan, Error> {
+    if start.len() != target.len() {
+        return Err(Error::DifferentStackCount {
+            start: start.len(),
+            target: target.len(),
+        });
+    }
+    let sorted = |stacks: &[Vec<String>]| {
+        let mut crates = stacks.concat();
+        crates.sort_unstable();
+        crates
+    };
+    if sorted(start) != sorted(target) {
+        return Err(Error::DifferentCrates);
+    }
+    let number =
+        |index: usize| u8::try_from(index + 1).map_err(|_| Error::TooManyStacks(start.len()));
+    number(start.len().saturating_sub(1))?;
+
+    // arrangements seen so far with how they were reached and in how many instructions
+    let mut arrangements: Vec<Vec<Vec<String>>> = vec![start.to_vec()];
+    let mut parents: Vec<Option<(usize, Instruction)>> = vec![None];
+    let mut distances: Vec<usize> = vec![0];
+    let mut seen: HashMap<Vec<Vec<String>>, usize> = HashMap::from([(start.to_vec(), 0)]);
+    let mut queue = BinaryHeap::from([Reverse((estimate(start, target), 0, 0_usize))]);
+    let mut explored = 0;
+
+    while let Some(Reverse((_, distance, id))) = queue.pop() {
+        // a shorter way to the same arrangement was found after this one was queued
+        if seen[&arrangements[id]] != id {
+            continue;
+        }
+        if arrangements[id] == target {
+            let mut instructions = vec![];
+            let mut current = id;
+            while let Some((parent, instruction)) = parents[current].take() {
+                instructions.push(instruction);
+                current = parent;
+            }
+            instructions.reverse();
+            return Ok(Plan {
+                start: start.to_vec(),
+                instructions,
+            });
+        }
+        explored += 1;
+        if explored > limit {
+            return Err(Error::SearchLimit(limit));
+        }
+
+        for src in 0..start.len() {
+            for dest in (0..start.len()).filter(|&dest| dest != src) {
+                for count in 1..=arrangements[id][src].len() {
+                    let mut next = arrangements[id].clone();
+                    crane.move_crates(&mut next, count, src, dest);
+                    let next_distance = distance + 1;
+                    if seen
+                        .get(&next)
+                        .is_some_and(|&known| distances[known] <= next_distance)
+                    {
+                        continue;
+                    }
+
+                    let instruction = Instruction {
+                        repetitions: u32::try_from(count).unwrap_or(u32::MAX),
+                        src: number(src)?,
+                        dest: number(dest)?,
+                    };
+                    let next_id = arrangements.len();
+                    queue.push(Reverse((
+                        next_distance + estimate(&next, target),
+                        next_distance,
+                        next_id,
+                    )));
+                    seen.insert(next.clone(), next_id);
+                    arrangements.push(next);
+                    parents.push(Some((id, instruction)));
+                    distances.push(next_distance);
+                }
+            }
+        }
+    }
+    Err(Error::SearchLimit(explored))
+}
+
+/// [`plan`] between two drawings in the puzzle format.
+///
+/// # Errors
+/// [`Error::Drawing`] if a drawing can not be parsed, otherwise see [`plan`]
+pub fn plan_drawings(
+    start: &str,
+    target: &str,
+    crane: &dyn CraneModel,
+    limit: usize,
+) -> Result<Plan, Error> {
+    let parse = |text: &str| drawing::parse(text).map_err(Error::Drawing);
+    plan(&parse(start)?, &parse(target)?, crane, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plan, plan_drawings, Error};
+    use crate::day_5::{
+        crane::LimitedLift,
+        drawing::{render, Error as DrawingError},
+        simulate, simulate_crane,
+        tests::from_chars,
+        Crane,
+    };
+
+    const START: &str = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n";
+
+    #[test]
+    fn example() {
+        // final stacks of the example program: C, M, PDNZ and M, C, PZND
+        for (crane, target, answer) in [
+            (
+                Crane::CrateMover9000,
+                "        [Z]\n        [N]\n        [D]\n[C] [M] [P]\n 1   2   3 \n",
+                "CMZ",
+            ),
+            (
+                Crane::CrateMover9001,
+                "        [D]\n        [N]\n        [Z]\n[M] [C] [P]\n 1   2   3 \n",
+                "MCD",
+            ),
+        ] {
+            let plan = plan_drawings(START, target, &crane, 10_000).unwrap();
+            // the example needs four instructions, a shorter program is fine
+            assert!(plan.instructions.len() <= 4);
+
+            let program = plan.program();
+            assert_eq!(simulate_crane(&program, &crane).unwrap().concat(), answer);
+            assert_eq!(render(&simulate(&program, &crane).unwrap().stacks), target);
+        }
+    }
+
+    #[test]
+    fn shortest() {
+        let start = from_chars(&["AB", "", "C"]);
+        let target = from_chars(&["", "AB", "C"]);
+
+        let plan_9001 = plan(&start, &target, &Crane::CrateMover9001, 1000).unwrap();
+        assert_eq!(plan_9001.to_string(), "move 2 from 1 to 2\n");
+
+        // one crate at a time reverses, so the crates have to make a detour
+        let plan_9000 = plan(&start, &target, &Crane::CrateMover9000, 1000).unwrap();
+        assert_eq!(plan_9000.instructions.len(), 2);
+        let stacks = simulate(&plan_9000.program(), &Crane::CrateMover9000)
+            .unwrap()
+            .stacks;
+        assert_eq!(stacks, target);
+
+        let limited = LimitedLift {
+            capacity: 1,
+            reverse: true,
+        };
+        assert_eq!(plan(&start, &start, &limited, 0).unwrap().instructions, []);
+    }
+
+    #[test]
+    fn errors() {
+        let start = from_chars(&["AB", "C"]);
+        assert_eq!(
+            plan(&start, &from_chars(&["ABC"]), &Crane::CrateMover9000, 100),
+            Err(Error::DifferentStackCount {
+                start: 2,
+                target: 1
+            })
+        );
+        assert_eq!(
+            plan(
+                &start,
+                &from_chars(&["AB", "D"]),
+                &Crane::CrateMover9000,
+                100
+            ),
+            Err(Error::DifferentCrates)
+        );
+        assert_eq!(
+            plan(&start, &from_chars(&["CBA", ""]), &Crane::CrateMover9000, 1),
+            Err(Error::SearchLimit(1))
+        );
+        assert_eq!(
+            plan_drawings(START, "[Z] [M] [P]\n", &Crane::CrateMover9000, 100),
+            Err(Error::Drawing(DrawingError::MissingNumberLine))
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! Crane programs on their own, without a drawing.
+//!
+//! A program has one instruction per line, either `move N from A to B` or the shorthand
+//! `N A->B`. Anything after `#` is a comment and blank lines are skipped.
+//!
+//! [`normalize`] shortens a program without changing what it does to the stacks. Whether two
+//! moves can be merged depends on the crane, so every rewrite is first tried on a few labelled
+//! crates with the crane it is meant for. Moves of more than [`PROBE_LIMIT`] crates are tried
+//! with that many, so a crane is expected to treat huge moves like large ones.
+
+use std::fmt::Display;
+
+use super::{crane::CraneModel, parser::parse_instructions, Instruction};
+use crate::parsing::ParseError;
+
+/// Why a line of a program is not an instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxError {
+    /// neither `move N from A to B` nor `N A->B`
+    Unrecognized,
+    /// stacks go up to 255 and counts up to `u32::MAX`
+    NumberOutOfRange,
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyntaxError::Unrecognized => {
+                write!(f, "expected \"move N from A to B\" or \"N A->B\"")
+            }
+            SyntaxError::NumberOutOfRange => write!(f, "number out of range"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    /// `move N from A to B`
+    Canonical,
+    /// `N A->B`
+    Shorthand,
+}
+
+/// Instructions of a program.
+///
+/// # Errors
+/// the first line that is neither an instruction, a comment nor blank
+pub fn parse_program(program: &str) -> Result<Vec<Instruction>, ParseError<SyntaxError>> {
+    parse_instructions(program, 1)
+}
+
+/// One instruction per line, every line ends with a new line.
+#[must_use]
+pub fn format_program(instructions: &[Instruction], syntax: Syntax) -> String {
+    instructions
+        .iter()
+        .map(|instruction| match syntax {
+            Syntax::Canonical => format!("{instruction}\n"),
+            Syntax::Shorthand => format!("{}\n", instruction.shorthand()),
+        })
+        .collect()
+}
+
+/// Most crates a single move is tried with, however many it moves
+pub const PROBE_LIMIT: usize = 1024;
+
+/// Runs `moves` of `(count, src, dest)` with `crane` on a source stack of labelled crates and a
+/// destination stack with one crate of its own.
+fn probe(crane: &dyn CraneModel, size: usize, moves: &[(usize, usize, usize)]) -> Vec<Vec<String>> {
+    let mut stacks = vec![
+        (0..size).map(|label| label.to_string()).collect(),
+        vec!["dest".to_string()],
+    ];
+    for &(count, src, dest) in moves {
+        crane.move_crates(&mut stacks, count, src, dest);
+    }
+    stacks
+}
+
+/// Moves nothing whatever the stacks look like
+fn is_no_op(instruction: &Instruction, crane: &dyn CraneModel) -> bool {
+    let count = (instruction.repetitions as usize).min(PROBE_LIMIT);
+    count == 0
+        || (instruction.src == instruction.dest
+            && probe(crane, count, &[(count, 0, 0)]) == probe(crane, count, &[]))
+}
+
+/// What two neighbouring instructions can be replaced with
+enum Merge {
+    Replace(Instruction),
+    Cancel,
+}
+
+/// `None` when `first` followed by `second` can not be merged
+fn merge(first: &Instruction, second: &Instruction, crane: &dyn CraneModel) -> Option<Merge> {
+    let (a, b) = (first.repetitions, second.repetitions);
+
+    if (first.src, first.dest) == (second.src, second.dest) {
+        // the source has to hold all crates for the pair as well
+        let repetitions = a.checked_add(b)?;
+        let (a, b) = ((a as usize).min(PROBE_LIMIT), (b as usize).min(PROBE_LIMIT));
+        let pair = probe(crane, a + b, &[(a, 0, 1), (b, 0, 1)]);
+        return (pair == probe(crane, a + b, &[(a + b, 0, 1)]))
+            .then(|| Merge::Replace(Instruction::new(repetitions, first.src, first.dest)));
+    }
+
+    // moving some of the crates straight back; more would take crates from the destination
+    if (first.src, first.dest) == (second.dest, second.src) && b <= a {
+        let rest = a - b;
+        // the probe keeps whether any crates stay on the destination
+        let (b, kept) = (
+            (b as usize).min(PROBE_LIMIT),
+            (rest as usize).min(PROBE_LIMIT),
+        );
+        let pair = probe(crane, b + kept, &[(b + kept, 0, 1), (b, 1, 0)]);
+        if pair != probe(crane, b + kept, &[(kept, 0, 1)]) {
+            return None;
+        }
+        return Some(if rest == 0 {
+            Merge::Cancel
+        } else {
+            Merge::Replace(Instruction {
+                repetitions: rest,
+                ..first.clone()
+            })
+        });
+    }
+    None
+}
+
+/// Drops instructions that move nothing and merges neighbouring instructions that `crane`
+/// executes the same way as one. The result leaves the stacks as the original program does, but
+/// may succeed where the original fails: two moves that cancel out are dropped even when the
+/// source stack is too small for them.
+#[must_use]
+pub fn normalize(instructions: &[Instruction], crane: &dyn CraneModel) -> Vec<Instruction> {
+    let mut program: Vec<Instruction> = vec![];
+    for instruction in instructions {
+        let mut current = Some(instruction.clone());
+        while let (Some(last), Some(next)) = (program.last(), &current) {
+            let Some(merged) = merge(last, next, crane) else {
+                break;
+            };
+            program.pop();
+            current = match merged {
+                Merge::Replace(instruction) => Some(instruction),
+                Merge::Cancel => None,
+            };
+        }
+        if let Some(instruction) = current.filter(|instruction| !is_no_op(instruction, crane)) {
+            program.push(instruction);
+        }
+    }
+    program
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_program, normalize, parse_program, Syntax, SyntaxError};
+    use crate::day_5::{
+        crane::LimitedLift, drawing::render, simulate, tests::EXAMPLE_INPUT, Crane, Instruction,
+    };
+
+    #[test]
+    fn syntax() {
+        let program = "# example\nmove 1 from 2 to 1\n3 1->3\n\n  2 2 -> 1  # two\n1 1->2\n";
+        let instructions = parse_program(program).unwrap();
+        assert_eq!(instructions.len(), 4);
+
+        let canonical = format_program(&instructions, Syntax::Canonical);
+        assert!(EXAMPLE_INPUT.ends_with(canonical.trim_end()));
+        assert_eq!(
+            format_program(&instructions, Syntax::Shorthand),
+            "1 2->1\n3 1->3\n2 2->1\n1 1->2\n"
+        );
+        assert_eq!(parse_program(&canonical).unwrap(), instructions);
+
+        let error = parse_program("1 1->2\nmove 1 to 2").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(error.reason, SyntaxError::Unrecognized);
+    }
+
+    #[test]
+    fn normalization() {
+        let program =
+            parse_program("1 1->2\n2 1->2\n0 2->3\n3 2->2\n2 2->1\n1 2->1\n1 3->1").unwrap();
+
+        // the crates all come back and only the last move is left
+        for crane in [Crane::CrateMover9000, Crane::CrateMover9001] {
+            assert_eq!(normalize(&program, &crane), [Instruction::new(1, 3, 1)]);
+        }
+
+        // merging changes the order when all crates are moved at once
+        let program = parse_program("1 1->2\n2 1->2\n1 2->1").unwrap();
+        let one = normalize(&program, &Crane::CrateMover9000);
+        assert_eq!(format_program(&one, Syntax::Shorthand), "2 1->2\n");
+        let many = normalize(&program, &Crane::CrateMover9001);
+        assert_eq!(many, program);
+
+        // a reversing crane turns a stack upside down when moving onto the same stack
+        let reversing = LimitedLift {
+            capacity: 3,
+            reverse: true,
+        };
+        let in_place = [Instruction::new(3, 2, 2)];
+        assert_eq!(normalize(&in_place, &reversing), in_place);
+        assert_eq!(normalize(&in_place, &Crane::CrateMover9000), []);
+    }
+
+    #[test]
+    fn huge_counts() {
+        let program = parse_program("move 3000000000 from 1 to 2\nmove 1 from 1 to 2").unwrap();
+        assert_eq!(
+            normalize(&program, &Crane::CrateMover9000),
+            [Instruction::new(3_000_000_001, 1, 2)]
+        );
+        assert_eq!(normalize(&program, &Crane::CrateMover9001), program);
+
+        let program = parse_program("4000000000 1->2\n3999999999 2->1\n4000000000 3->3").unwrap();
+        assert_eq!(
+            normalize(&program, &Crane::CrateMover9000),
+            [Instruction::new(1, 1, 2)]
+        );
+    }
+
+    #[test]
+    fn same_result() {
+        let drawing = "[A]        \n[B] [C]    \n[D] [E] [F]\n 1   2   3 \n";
+        let program = "2 1->2\n1 1->2\n1 2->1\n2 2->3\n1 3->3\n2 3->2\n1 3->1\n0 1->2\n1 2->1";
+        let cranes = [Crane::CrateMover9000, Crane::CrateMover9001];
+        for crane in cranes {
+            let normalized = normalize(&parse_program(program).unwrap(), &crane);
+            let input = |program: &str| format!("{drawing}\n{program}");
+            let expected = simulate(&input(program), &crane).unwrap().stacks;
+            let output = simulate(
+                &input(&format_program(&normalized, Syntax::Canonical)),
+                &crane,
+            )
+            .unwrap()
+            .stacks;
+            assert_eq!(render(&output), render(&expected));
+        }
+    }
+}
@@ -1,68 +1,100 @@
-use std::{fmt::Display, ops::Deref, rc::Rc};
+use std::{collections::HashMap, fmt::Display};
 
-impl TryFrom<&'static str> for Command {
-    type Error = Error;
+use indextree::{Arena, NodeId};
 
-    fn try_from(value: &'static str) -> Result<Self, Self::Error> {
-        // "cd arg"
-        // "ls"
-        match &value[0..=1] {
-            "cd" => {
-                // strip off "cd "
-                let cd = ChangeDir::try_from(consume(value, "cd "))?;
-                Ok(Command::Cd(cd))
-            }
-            "ls" => Ok(Command::Ls),
-            _ => Err(Error::Command(ErrorParsingDetails {
-                input: value,
-                was_about_to_parse: Some(&value[0..=1]),
-                expected: vec!["cd".into(), "ls".into()],
-            })),
-        }
-    }
-}
-impl TryFrom<&'static str> for Line {
-    type Error = Error;
+pub mod cleanup;
+pub mod disk;
+pub mod query;
+pub mod report;
+pub mod snapshot;
+pub mod transcript;
 
-    fn try_from(s: &'static str) -> Result<Self, Self::Error> {
-        // "$..." -> Command
-        // "_" -> Not Command
+use self::transcript::{Inconsistency, Merge};
 
-        let first_char = &s.chars().peekable().next().expect("empty input");
+impl TryFrom<&str> for Command {
+    type Error = Error;
 
-        if let '$' = *first_char {
-            let cmd = Command::try_from(consume(s, "$ "))?;
-            Ok(Line::CommandInput(cmd))
-        } else {
-            Ok(Line::CommandOutput(FileNode::from(s)))
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        // "cd arg"
+        // "ls -l"
+        // "mkdir -p 'a b/c'"
+        let error = |was_about_to_parse: Option<&str>, expected: &[&str]| {
+            Error::Command(ErrorParsingDetails {
+                line: None,
+                input: value.to_string(),
+                was_about_to_parse: was_about_to_parse.map(str::to_string),
+                expected: expected.iter().map(|&expected| expected.into()).collect(),
+            })
+        };
+        let Some(words) = words(value) else {
+            // only the commands that change what the tree looks like have to be readable
+            return match value.split_whitespace().next() {
+                Some("cd" | "ls") => Err(error(None, &["closing quote"])),
+                _ => Ok(Command::Other),
+            };
+        };
+        let Some((name, arguments)) = words.split_first() else {
+            return Ok(Command::Other);
+        };
+        // everything after `--` is an operand, even if it starts with `-`
+        let (options, rest) = match arguments.iter().position(|argument| argument == "--") {
+            Some(end) => (&arguments[..end], &arguments[end + 1..]),
+            None => (arguments, &[][..]),
+        };
+        let (flags, mut operands): (Vec<&String>, Vec<&String>) = options
+            .iter()
+            .partition(|argument| argument.len() > 1 && argument.starts_with('-'));
+        operands.extend(rest);
+        let has_flag = |short: char, long: &str| {
+            flags.iter().any(|flag| match flag.strip_prefix("--") {
+                Some(flag) => flag == long,
+                None => flag.contains(short),
+            })
+        };
+        let operands = operands.into_iter().cloned().collect::<Vec<String>>();
+
+        match (name.as_str(), operands.as_slice()) {
+            ("cd", [path]) => Ok(Command::Cd(ChangeDir::steps(path))),
+            ("cd", _) => Err(error(Some(value), &["..", "/", "<some dir name>"])),
+            ("ls", []) => Ok(Command::Ls(vec![])),
+            ("ls", [path]) => Ok(Command::Ls(ChangeDir::steps(path))),
+            ("ls", _) => Err(error(Some(value), &["<at most one dir name>"])),
+            ("pwd", _) => Ok(Command::Pwd),
+            ("mkdir", _) => Ok(Command::Mkdir {
+                parents: has_flag('p', "parents"),
+                paths: operands,
+            }),
+            ("rm", _) => Ok(Command::Rm {
+                recursive: has_flag('r', "recursive") || has_flag('R', "recursive"),
+                paths: operands,
+            }),
+            ("touch", _) => Ok(Command::Touch(operands)),
+            _ => Ok(Command::Other),
         }
     }
 }
-impl TryFrom<&'static str> for ChangeDir {
+impl TryFrom<&str> for Line {
     type Error = Error;
 
-    fn try_from(input: &'static str) -> Result<Self, Self::Error> {
-        let mut peek = input.chars().peekable();
-        let (first, second) = (peek.next(), peek.next());
-        match (first, second) {
-            (Some('.'), Some('.')) => Ok(Self::Up),
-            (Some('/'), None) => Ok(Self::Root),
-            (None, None) => Err(Error::Command(ErrorParsingDetails {
-                input,
-                was_about_to_parse: None,
-                expected: vec!["..".into(), "/".into(), "<some dir name>".into()],
-            })),
-            (_, _) => Ok(Self::Dir(input)),
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        // "$..." -> Command
+        // "_" -> Not Command, its meaning depends on the command before it
+        match s.strip_prefix('$') {
+            Some(command) => Ok(Line::CommandInput(Command::try_from(command.trim())?)),
+            None => Ok(Line::CommandOutput(s.to_string())),
         }
     }
 }
 impl ChangeDir {
-    fn get_name(&self) -> &'static str {
-        match self {
-            ChangeDir::Up => todo!("get parent name"),
-            ChangeDir::Root => todo!("get root"),
-            ChangeDir::Dir(dir) => dir,
-        }
+    /// Steps to follow `path`, starting at the root if it is absolute
+    fn steps(path: &str) -> Vec<Self> {
+        let root = path.starts_with('/').then_some(ChangeDir::Root);
+        let components = path.split('/').filter_map(|component| match component {
+            "" | "." => None,
+            ".." => Some(ChangeDir::Up),
+            name => Some(ChangeDir::Dir(name.to_string())),
+        });
+        root.into_iter().chain(components).collect()
     }
 }
 impl Display for File {
@@ -70,325 +102,545 @@ impl Display for File {
         write!(f, "{} {}", self.size, self.name)
     }
 }
-impl From<&'static str> for File {
-    fn from(s: &'static str) -> Self {
+impl Display for Directory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "dir {}", self.name)
+    }
+}
+impl TryFrom<&str> for File {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
         // 62596 h.lst -> size = 62596, name = "h.lst"
-        let mut size = String::new();
-        for character in s.chars() {
-            match character {
-                '0'..='9' => size.push(character),
-                ' ' => break,
-                _ => todo!("convert to fallible function"),
-            }
-        }
-        Self {
-            size: size
-                .parse::<usize>()
-                .expect("failed to parse a string into a number"), // should never happen
-            name: &s[size.len() + 1..],
-            parent: None,
-        }
+        let error = || listing_error(s);
+        let (size, name) = s.split_once(' ').ok_or_else(error)?;
+        let size = size.parse::<usize>().map_err(|_| error())?;
+        Ok(Self::new(unquote(name).ok_or_else(error)?, size))
     }
 }
 impl FileNode {
-    fn get_name(&self) -> &'static str {
+    #[must_use]
+    pub fn get_name(&self) -> &str {
         match self {
-            FileNode::Directory(a) => a.name,
-            FileNode::File(a) => a.name,
+            FileNode::Directory(a) => &a.name,
+            FileNode::File(a) => &a.name,
         }
     }
 
-    fn is_directory(&self) -> bool {
-        match &self {
-            FileNode::Directory(_) => true,
-            FileNode::File(_) => false,
+    /// Absolute path, empty until the node is added to a [`Tree`]
+    #[must_use]
+    pub fn get_path(&self) -> &str {
+        match self {
+            FileNode::Directory(a) => &a.path,
+            FileNode::File(a) => &a.path,
         }
     }
 
-    fn is_file(&self) -> bool {
-        !self.is_directory()
-    }
-
-    fn set_parent(&mut self, parent_node: Rc<FileNode>) {
+    fn set_path(&mut self, path: String) {
         match self {
-            FileNode::Directory(dir) => dir.parent = Some(parent_node),
-            FileNode::File(file) => file.parent = Some(parent_node),
+            FileNode::Directory(a) => a.path = path,
+            FileNode::File(a) => a.path = path,
         }
     }
 
-    fn get_parent(&self) -> Option<Rc<FileNode>> {
-        match self {
-            FileNode::Directory(dir) => dir.parent.clone(),
-            FileNode::File(file) => file.parent.clone(),
+    #[must_use]
+    pub fn is_directory(&self) -> bool {
+        match &self {
+            FileNode::Directory(_) => true,
+            FileNode::File(_) => false,
         }
     }
-    fn get_children(&self) -> Vec<Rc<FileNode>> {
-        match self {
-            FileNode::Directory(dir) => dir.children.clone(),
-            FileNode::File(_) => panic!("requested children but this is a file"),
-        }
+
+    #[must_use]
+    pub fn is_file(&self) -> bool {
+        !self.is_directory()
     }
 
-    fn get_directory(&self) -> &Directory {
+    /// # Panics
+    /// if this is a file
+    #[must_use]
+    pub fn get_directory(&self) -> &Directory {
         match self {
             FileNode::Directory(dir) => dir,
             FileNode::File(_) => panic!("requested directory but this is a file"),
         }
     }
-    fn get_file(&self) -> &File {
+
+    /// # Panics
+    /// if this is a directory
+    #[must_use]
+    pub fn get_file(&self) -> &File {
         match self {
             FileNode::Directory(_) => panic!("requested file but this is a directory"),
             FileNode::File(file) => file,
         }
     }
 }
-impl From<&'static str> for FileNode {
-    fn from(s: &'static str) -> Self {
+impl TryFrom<&str> for FileNode {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
         // dir e
         // 29116 f.txt
-        let first_four_characters = &s[0..=3];
-        match first_four_characters {
-            // hmm... we don't know the contents of the directory just yet
-            // construct it from path or make a tree like structure maybe?
-            "dir " => Self::Directory(Directory::from(&s[4..])),
-            _ => Self::File(File::from(s)),
+        // drwxr-xr-x 2 user group  4096 Dec  7 10:00 e
+        // -rw-r--r-- 1 user group 29116 Dec  7 10:00 'f g.txt'
+        if let Some(name) = s.strip_prefix("dir ") {
+            // the contents of the directory are listed once we cd into it
+            let name = unquote(name).ok_or_else(|| listing_error(s))?;
+            return Ok(Self::Directory(Directory::new(name)));
+        }
+        match long_listing(s) {
+            Some(('d', _, name)) => Ok(Self::Directory(Directory::new(name))),
+            Some((_, size, name)) => Ok(Self::File(File::new(name, size))),
+            None => File::try_from(s).map(Self::File),
         }
     }
 }
-impl Directory {
-    fn from(s: &'static str) -> Self {
-        Self {
-            name: s,
-            children: vec![],
-            parent: None,
+fn listing_error(s: &str) -> Error {
+    Error::Command(ErrorParsingDetails {
+        line: None,
+        input: s.to_string(),
+        was_about_to_parse: None,
+        expected: vec![
+            "dir <name>".into(),
+            "<size> <name>".into(),
+            "<ls -l line>".into(),
+        ],
+    })
+}
+
+/// A line of `ls` output, `None` for the lines that are not an entry: blank lines, the total of
+/// `ls -l`, `.` and `..`, and entries that are neither files, links nor directories
+fn listing_entry(s: &str) -> Result<Option<FileNode>, Error> {
+    let s = s.trim_end();
+    if s.is_empty() || s.starts_with("total ") {
+        return Ok(None);
+    }
+    if let Some((kind, _, _)) = long_listing(s) {
+        if !matches!(kind, '-' | 'd' | 'l') {
+            return Ok(None);
         }
     }
+    let file_node = FileNode::try_from(s)?;
+    Ok(Some(file_node).filter(|node| !matches!(node.get_name(), "." | "..")))
+}
+
+/// Kind, size and name of an `ls -l` line. Dates are either `Dec  7 10:00` or `2022-12-07 10:00`
+/// and links are named without their target.
+fn long_listing(s: &str) -> Option<(char, usize, String)> {
+    let (columns, rest) = fields(s, 5)?;
+    let mode = columns[0].chars().collect::<Vec<char>>();
+    let is_mode = mode.len() >= 10
+        && "-dlcbps".contains(mode[0])
+        && mode[1..10].iter().all(|&c| "rwxsStT-".contains(c));
+    if !is_mode {
+        return None;
+    }
+    let size = columns[4].parse::<usize>().ok()?;
+    let month = rest.starts_with(|c: char| c.is_alphabetic());
+    let (_, name) = fields(rest, if month { 3 } else { 2 })?;
+    let name = match mode[0] {
+        'l' => name.split_once(" -> ").map_or(name, |(name, _)| name),
+        _ => name,
+    };
+    Some((mode[0], size, unquote(name)?))
+}
 
-    fn get_size(&self) -> usize {
-        let mut sum = 0;
-        for child in &self.children {
-            sum += match child.deref() {
-                FileNode::Directory(dir) => dir.get_size(),
-                FileNode::File(file) => file.size,
-            }
+/// The first `count` whitespace separated fields of `s` and the rest after them
+fn fields(s: &str, count: usize) -> Option<(Vec<&str>, &str)> {
+    let mut rest = s;
+    let mut fields = vec![];
+    for _ in 0..count {
+        rest = rest.trim_start();
+        let end = rest.find(char::is_whitespace)?;
+        fields.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    Some((fields, rest.trim_start()))
+}
+
+/// A name the way `ls` prints it: quoted, with escaped characters or as it is
+fn unquote(name: &str) -> Option<String> {
+    if name.starts_with(['\'', '"']) || name.contains('\\') {
+        match words(name)?.as_slice() {
+            [name] => Some(name.clone()),
+            _ => None,
         }
-        sum
+    } else {
+        Some(name.to_string()).filter(|name| !name.is_empty())
     }
 }
 
-/// Effectively removes `eat` from `input` without copying
-fn consume(input: &'static str, eat: &str) -> &'static str {
-    for (input_ch, eat_ch) in input.chars().zip(eat.chars()) {
-        assert_eq!(
-            input_ch, eat_ch,
-            "failed to consume '{}' from the start of '{}'",
-            eat, input
-        );
+/// `name` as a single shell word that [`words`] and [`unquote`] read back, in single quotes
+/// unless it only has characters without a special meaning
+fn quote(name: &str) -> String {
+    let plain = |c: char| c.is_alphanumeric() || "._-+,@%=:".contains(c);
+    if !name.is_empty() && name.chars().all(plain) {
+        name.to_string()
+    } else {
+        format!("'{}'", name.replace('\'', r"'\''"))
     }
-    &input[eat.len()..]
+}
+
+/// Whether `name` is a single path component that stays in its directory
+fn is_name(name: &str) -> bool {
+    !matches!(name, "" | "." | "..") && !name.contains(['/', '\0'])
+}
+
+/// Splits a command line into words like a shell: single quotes keep everything, double quotes
+/// keep everything but `\"` and `\\`, and a backslash outside quotes escapes the next character.
+/// `None` if a quote is not closed.
+fn words(s: &str) -> Option<Vec<String>> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut characters = s.chars();
+    while let Some(character) = characters.next() {
+        match character {
+            character if character.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match characters.next()? {
+                        '\'' => break,
+                        character => word.push(character),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match characters.next()? {
+                        '"' => break,
+                        '\\' => match characters.next()? {
+                            escaped @ ('"' | '\\') => word.push(escaped),
+                            other => {
+                                word.push('\\');
+                                word.push(other);
+                            }
+                        },
+                        character => word.push(character),
+                    }
+                }
+            }
+            '\\' => word
+                .get_or_insert_with(String::new)
+                .push(characters.next()?),
+            character => word.get_or_insert_with(String::new).push(character),
+        }
+    }
+    words.extend(word);
+    Some(words)
 }
 
 #[derive(Debug)]
 pub enum Error {
     Command(ErrorParsingDetails),
-    DirectoryNotFound,
+    /// contradictions found while replaying a transcript strictly
+    Inconsistent(Vec<Inconsistency>),
+}
+
+impl Error {
+    /// Adds the 1-based transcript line to a parsing error
+    fn on_line(self, line: usize) -> Self {
+        match self {
+            Error::Command(details) => Error::Command(ErrorParsingDetails {
+                line: Some(line),
+                ..details
+            }),
+            error @ Error::Inconsistent(_) => error,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Command(details) => {
+                if let Some(line) = details.line {
+                    write!(f, "line {line}: ")?;
+                }
+                write!(f, "failed to create command\nExpected one of: [ ")?;
+                for expected in &details.expected {
+                    write!(f, "'{expected}' ")?;
+                }
+                writeln!(f, "]")?;
+                if let Some(attempt) = &details.was_about_to_parse {
+                    writeln!(f, "attempted to parse '{attempt}'")?;
+                }
+                write!(f, "full input: {}", details.input)
+            }
+            Error::Inconsistent(inconsistencies) => {
+                write!(f, "the transcript contradicts itself")?;
+                for inconsistency in inconsistencies {
+                    write!(f, "\n{inconsistency}")?;
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct ErrorParsingDetails {
-    input: &'static str,
-    was_about_to_parse: Option<&'static str>,
+    /// 1-based line of the transcript, if the line was part of one
+    line: Option<usize>,
+    input: String,
+    was_about_to_parse: Option<String>,
     expected: Vec<String>,
 }
 #[derive(Debug)]
 enum Line {
     CommandInput(Command),
-    CommandOutput(FileNode),
+    CommandOutput(String),
 }
 #[derive(Debug)]
 enum Command {
-    Ls,
-    Cd(ChangeDir),
+    Ls(Vec<ChangeDir>),
+    Cd(Vec<ChangeDir>),
+    Pwd,
+    Mkdir { parents: bool, paths: Vec<String> },
+    Rm { recursive: bool, paths: Vec<String> },
+    Touch(Vec<String>),
+    Other,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum ChangeDir {
-    Dir(&'static str),
+    Dir(String),
     Up,
     Root,
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FileNode {
     Directory(Directory),
     File(File),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct File {
-    name: &'static str,
-    size: usize,
-    parent: Option<Rc<FileNode>>,
+    pub name: String,
+    pub size: usize,
+    path: String,
 }
 
-#[derive(Debug, Clone)]
+impl File {
+    fn new(name: String, size: usize) -> Self {
+        Self {
+            name,
+            size,
+            path: String::new(),
+        }
+    }
+
+    /// Absolute path, e.g. `/a/h.lst`
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Directory {
-    name: &'static str,
-    children: Vec<Rc<FileNode>>,
-    parent: Option<Rc<FileNode>>,
+    pub name: String,
+    path: String,
 }
 
-pub fn print_error(error: Error) {
-    match error {
-        Error::Command(details) => {
-            let mut output = String::from("failed to create command\nExpected on of: [ ");
-            for expected in &details.expected {
-                output.push_str(&format!("'{}' ", expected.as_str()));
-            }
-            output.push_str("]\n");
-            if let Some(attempt) = details.was_about_to_parse {
-                output.push_str(&format!("attempted to parse '{}'\n", attempt));
-            }
-            output.push_str(&format!("full input: {}", details.input));
-            println!("{}", output);
-        }
-        Error::DirectoryNotFound => {
-            println!("Cannot change directory because target directory was not found");
+impl Directory {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            path: String::new(),
         }
     }
+
+    /// Absolute path, e.g. `/a/e` or `/` for the root
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
 }
 
-#[derive(Clone)]
-struct Tree {
-    nodes: Vec<FileNode>,
-    current_working_directory: Option<Rc<FileNode>>,
+pub fn print_error(error: &Error) {
+    println!("{error}");
+}
+
+/// The file system as far as the transcript shows it. Nodes live in an arena and refer to each
+/// other by [`NodeId`], the root directory is `/`.
+#[derive(Debug, Clone)]
+pub struct Tree {
+    nodes: Arena<FileNode>,
+    root: NodeId,
+    current_working_directory: NodeId,
 }
 
 impl Tree {
     fn new(root: Directory) -> Self {
-        let root = FileNode::Directory(root);
-        let nodes = vec![root];
-
-        let tmp = Self {
-            nodes,
-            current_working_directory: None,
-        };
+        let mut nodes = Arena::new();
+        let root = nodes.new_node(FileNode::Directory(root));
         Self {
-            nodes: tmp.nodes.clone(),
-            current_working_directory: tmp.get_root().map(Rc::new),
+            nodes,
+            root,
+            current_working_directory: root,
         }
     }
 
-    fn get_root(self) -> Option<FileNode> {
-        self.nodes.first().cloned()
+    #[must_use]
+    pub fn root(&self) -> NodeId {
+        self.root
     }
 
-    fn add_child(&mut self, file_node: FileNode) {
-        // add node to the list, link parent and children together
-        let file_node = match file_node {
-            FileNode::Directory(dir) => FileNode::Directory(Directory {
-                name: dir.name,
-                children: dir.children,
-                parent: Some(self.current_working_directory.as_ref().unwrap().clone()),
-            }),
-            FileNode::File(file) => FileNode::File(File {
-                name: file.name,
-                size: file.size,
-                parent: Some(self.current_working_directory.as_ref().unwrap().clone()),
-            }),
-        };
+    /// # Panics
+    /// if `id` belongs to another tree
+    #[must_use]
+    pub fn get(&self, id: NodeId) -> &FileNode {
+        self.nodes[id].get()
+    }
 
-        self.nodes.push(file_node);
-    }
-
-    fn change_working_directory(self, target: ChangeDir) -> Self {
-        let next_working_directory = match target {
-            ChangeDir::Dir(target_name) => {
-                // check if target is child in current working directory
-                let children = self
-                    .current_working_directory
-                    .clone()
-                    .unwrap()
-                    .get_children();
-                let target = children
-                    .iter()
-                    .find(|child| child.get_name() == target_name);
-
-                
-                match target {
-                    Some(target) => target.clone(),
-                    None => {
-                        return Self {
-                            nodes: self.nodes.clone(),
-                            current_working_directory: self.get_root().map(Rc::new),
-                        }
-                    }
-                }
-            }
-            ChangeDir::Up => {
-                // Check if current working directory has a parent
-                let result = match self
-                    .current_working_directory
-                    .as_ref()
-                    .unwrap()
-                    .get_parent()
-                {
-                    Some(parent) => parent,
-                    None => {
-                        return Self {
-                            nodes: self.clone().nodes,
-                            current_working_directory: self
-                                .get_root()
-                                .map(Rc::new),
-                        }
-                    }
-                };
-                result
-            }
-            ChangeDir::Root => {
-                let result = self.nodes.first().cloned().unwrap();
-                Rc::new(result)
-            }
-        };
+    pub fn children(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        id.children(&self.nodes)
+    }
 
-        Self {
-            nodes: self.nodes,
-            current_working_directory: Some(next_working_directory),
+    /// # Panics
+    /// if `id` belongs to another tree
+    #[must_use]
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id].parent()
+    }
+
+    /// `id` and everything below it, parents before their children
+    pub fn descendants(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        id.descendants(&self.nodes)
+    }
+
+    /// Every directory, the root first
+    pub fn directories(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.descendants(self.root)
+            .filter(|&id| self.get(id).is_directory())
+    }
+
+    /// Size of a file or the size of every file below a directory
+    #[must_use]
+    pub fn get_size(&self, id: NodeId) -> usize {
+        match self.get(id) {
+            FileNode::File(file) => file.size,
+            FileNode::Directory(_) => self.children(id).map(|child| self.get_size(child)).sum(),
         }
     }
-}
 
-fn generate_file_structure(input: &'static str) -> Result<(), Error> {
-    let mut tree = Tree::new(Directory {
-        name: "/",
-        children: vec![],
-        parent: None,
-    });
+    /// Size of every file and directory, added up once from the files upwards
+    #[must_use]
+    pub fn sizes(&self) -> HashMap<NodeId, usize> {
+        let mut sizes = HashMap::new();
+        let nodes = self.descendants(self.root).collect::<Vec<NodeId>>();
+        for id in nodes.into_iter().rev() {
+            let size = match self.get(id) {
+                FileNode::File(file) => file.size,
+                FileNode::Directory(_) => self.children(id).map(|child| sizes[&child]).sum(),
+            };
+            sizes.insert(id, size);
+        }
+        sizes
+    }
+
+    /// Absolute path of `id`, `/` for the root
+    #[must_use]
+    pub fn path(&self, id: NodeId) -> &str {
+        self.get(id).get_path()
+    }
 
-    // parse lines
-    for line in input.lines() {
-        let parsed_line = Line::try_from(line)?;
+    /// Node at an absolute path like `/a/e/i`. Empty components and `.` are skipped and `..`
+    /// goes up, but never above the root.
+    #[must_use]
+    pub fn find(&self, path: &str) -> Option<NodeId> {
+        let relative = path.strip_prefix('/')?;
+        let mut id = self.root;
+        for component in relative.split('/') {
+            id = match component {
+                "" | "." => id,
+                ".." => self.parent(id).unwrap_or(self.root),
+                name => self.find_child(id, name)?,
+            };
+        }
+        Some(id)
+    }
 
-        tree = match parsed_line {
-            Line::CommandOutput(new_node) => {
-                tree.add_child(new_node);
-                tree
-            }
-            Line::CommandInput(command) => match command {
-                Command::Ls => tree,
-                Command::Cd(target) => tree.change_working_directory(target),
-            },
-        };
+    /// Number of directories between `id` and the root, 0 for the root itself
+    #[must_use]
+    pub fn depth(&self, id: NodeId) -> usize {
+        id.ancestors(&self.nodes).count() - 1
+    }
+
+    fn find_child(&self, parent: NodeId, name: &str) -> Option<NodeId> {
+        self.children(parent)
+            .find(|&child| self.get(child).get_name() == name)
+    }
+
+    /// Removes `id` and everything below it
+    fn remove(&mut self, id: NodeId) {
+        id.remove_subtree(&mut self.nodes);
+    }
+
+    /// Appends `file_node` to `parent` without looking for a child of the same name
+    fn add_child(&mut self, parent: NodeId, mut file_node: FileNode) -> NodeId {
+        let path = self.path(parent).trim_end_matches('/');
+        file_node.set_path(format!("{path}/{}", file_node.get_name()));
+        let child = self.nodes.new_node(file_node);
+        parent.append(child, &mut self.nodes);
+        child
     }
+}
+
+/// Rebuilds the file system from a terminal transcript of `cd`, `ls`, `pwd`, `mkdir`, `rm` and
+/// `touch`. Other commands are skipped with their output and contradictions in the transcript are
+/// merged leniently, see [`transcript::replay`].
+///
+/// # Errors
+/// if a line of `ls` output can not be read
+pub fn generate_file_structure(input: &str) -> Result<Tree, Error> {
+    transcript::replay(input, Merge::Lenient).map(|replay| replay.tree)
+}
+
+/// Sum of the sizes of all directories of at most 100000.
+///
+/// # Panics
+/// if the transcript can not be parsed
+#[must_use]
+pub fn sum_of_small_directories(input: &str) -> usize {
+    let tree = generate_file_structure(input).unwrap_or_else(|error| panic!("{error}"));
+    let sizes = tree.sizes();
+    tree.directories()
+        .map(|directory| sizes[&directory])
+        .filter(|&size| size <= 100_000)
+        .sum()
+}
 
-    todo!()
+/// Size of the smallest directory that frees enough space for the update.
+///
+/// # Panics
+/// if the transcript can not be parsed
+#[must_use]
+pub fn smallest_directory_to_delete(input: &str) -> usize {
+    const DISK: usize = 70_000_000;
+    const UPDATE: usize = 30_000_000;
+
+    let tree = generate_file_structure(input).unwrap_or_else(|error| panic!("{error}"));
+    let sizes = tree.sizes();
+    let missing = (sizes[&tree.root()] + UPDATE).saturating_sub(DISK);
+    tree.directories()
+        .map(|directory| sizes[&directory])
+        .filter(|&size| size >= missing)
+        .min()
+        .unwrap_or(0)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::ChangeDir;
+    use super::{
+        generate_file_structure, listing_entry, smallest_directory_to_delete,
+        sum_of_small_directories, words, Command, Error, FileNode, Line,
+    };
 
     const INPUT: &str = include_str!("../puzzle_input/day_7.txt");
-    const EXAMPLE_INPUT: [&str; 2] = [
-        r"$ cd /
+    pub const EXAMPLE_INPUT: &str = r"$ cd /
 $ ls
 dir a
 14848514 b.txt
@@ -410,33 +662,150 @@ $ ls
 4060174 j
 8033020 d.log
 5626152 d.ext
-7214296 k",
-        "",
-    ];
+7214296 k";
 
-    const ANSWER: [usize; 2] = [0, 0];
-    const EXAMPLE_ANSWER: [[usize; 2]; 2] = [[48_381_165, 0], [0, 0]];
+    const ANSWER: [usize; 2] = [1_490_523, 12_390_492];
+    const EXAMPLE_ANSWER: [usize; 2] = [95437, 24_933_642];
+
+    #[test]
+    fn tree() {
+        let tree = generate_file_structure(EXAMPLE_INPUT).unwrap();
+        assert_eq!(tree.get_size(tree.root()), 48_381_165);
+        assert_eq!(tree.directories().count(), 4);
+
+        let paths = tree
+            .directories()
+            .map(|directory| (tree.path(directory), tree.get_size(directory)))
+            .collect::<Vec<(&str, usize)>>();
+        assert_eq!(
+            paths,
+            [
+                ("/", 48_381_165),
+                ("/a", 94853),
+                ("/a/e", 584),
+                ("/d", 24_933_642),
+            ]
+        );
+
+        let sizes = tree.sizes();
+        assert_eq!(sizes.len(), 14);
+        assert!(tree
+            .descendants(tree.root())
+            .all(|id| sizes[&id] == tree.get_size(id)));
+    }
 
     #[test]
-    #[ignore]
     fn example() {
-        let what_is_this = ChangeDir::try_from("asdasd");
+        assert_eq!(sum_of_small_directories(EXAMPLE_INPUT), EXAMPLE_ANSWER[0]);
+        assert_eq!(
+            smallest_directory_to_delete(EXAMPLE_INPUT),
+            EXAMPLE_ANSWER[1]
+        );
+    }
 
-        let dir = match what_is_this {
-            Ok(dir) => dir,
-            Err(_) => unreachable!(""),
-        };
+    #[test]
+    fn problem() {
+        assert_eq!(sum_of_small_directories(INPUT), ANSWER[0]);
+        assert_eq!(smallest_directory_to_delete(INPUT), ANSWER[1]);
+    }
+
+    #[test]
+    fn shell_words() {
+        assert_eq!(
+            words(r#"mkdir -p 'a b' "c \"d\" \e" f\ g  h''"#).unwrap(),
+            ["mkdir", "-p", "a b", r#"c "d" \e"#, "f g", "h"]
+        );
+        assert_eq!(words("'it'\\''s'").unwrap(), ["it's"]);
+        assert_eq!(words("touch 'open"), None);
+    }
 
-        for (input, expected) in EXAMPLE_INPUT.iter().zip(EXAMPLE_ANSWER.iter()) {
-            // assert_eq!(generate_file_structure(input), expected[0]);
-            // assert_eq!(todo(input), expected[1]);
+    #[test]
+    fn commands() {
+        for (line, expected) in [
+            ("$ cd ..", "Cd([Up])"),
+            ("$ cd /a/./e", r#"Cd([Root, Dir("a"), Dir("e")])"#),
+            ("$ ls -la", "Ls([])"),
+            ("$ ls 'my docs'", r#"Ls([Dir("my docs")])"#),
+            ("$ pwd", "Pwd"),
+            (
+                "$ mkdir -p a/b c",
+                r#"Mkdir { parents: true, paths: ["a/b", "c"] }"#,
+            ),
+            ("$ rm -rf x", r#"Rm { recursive: true, paths: ["x"] }"#),
+            (
+                "$ rm --recursive x",
+                r#"Rm { recursive: true, paths: ["x"] }"#,
+            ),
+            ("$ touch a\\ b", r#"Touch(["a b"])"#),
+            ("$ cd -- -x", r#"Cd([Dir("-x")])"#),
+            ("$ rm -r -- -f", r#"Rm { recursive: true, paths: ["-f"] }"#),
+        ] {
+            let Line::CommandInput(command) = Line::try_from(line).unwrap() else {
+                panic!("{line} is not a command");
+            };
+            assert_eq!(format!("{command:?}"), expected);
+        }
+
+        // commands that do not change the tree are read as such, however they look
+        for other in ["x", "", "cat notes.txt", "echo 'open", "vim -"] {
+            assert!(
+                matches!(Command::try_from(other), Ok(Command::Other)),
+                "{other}"
+            );
         }
+        let Err(Error::Command(details)) = Command::try_from("cd a b") else {
+            panic!("cd takes one directory");
+        };
+        assert_eq!(details.was_about_to_parse.as_deref(), Some("cd a b"));
+        assert!(Command::try_from("cd").is_err());
+        assert!(Command::try_from("cd 'a").is_err());
+        assert!(Command::try_from("ls a b").is_err());
     }
 
     #[test]
-    #[ignore]
-    fn problem() {
-        // assert_eq!(generate_file_structure(INPUT), ANSWER[0]);
-        // assert_eq!(todo(INPUT), ANSWER[1]);
+    fn listings() {
+        let entry = |line: &str| {
+            listing_entry(line).unwrap().map(|node| match node {
+                FileNode::Directory(directory) => directory.to_string(),
+                FileNode::File(file) => file.to_string(),
+            })
+        };
+        assert_eq!(entry("dir e").as_deref(), Some("dir e"));
+        assert_eq!(entry("29116 f").as_deref(), Some("29116 f"));
+        assert_eq!(entry("5 a").as_deref(), Some("5 a"));
+        assert_eq!(
+            entry("12 two words.txt").as_deref(),
+            Some("12 two words.txt")
+        );
+        assert_eq!(entry("12 'it'\\''s'").as_deref(), Some("12 it's"));
+        assert_eq!(entry("total 24"), None);
+        assert_eq!(entry(""), None);
+
+        let long = [
+            ("drwxr-xr-x 2 user group 4096 Dec  7 10:00 e", Some("dir e")),
+            (
+                "-rw-r--r-- 1 user group 29116 Dec  7 10:00 'f g.txt'",
+                Some("29116 f g.txt"),
+            ),
+            (
+                "-rw-r--r--. 1 user group 62596 2022-12-07 10:00 h.lst",
+                Some("62596 h.lst"),
+            ),
+            (
+                "lrwxrwxrwx 1 user group 5 Dec  7  2021 link -> h.lst",
+                Some("5 link"),
+            ),
+            ("drwxr-xr-x 2 user group 4096 Dec  7 10:00 .", None),
+            ("drwxr-xr-x 2 user group 4096 Dec  7 10:00 ..", None),
+            ("prw-r--r-- 1 user group 0 Dec  7 10:00 fifo", None),
+        ];
+        for (line, expected) in long {
+            assert_eq!(entry(line).as_deref(), expected, "{line}");
+        }
+
+        let Err(error) = generate_file_structure("$ cd /\n$ ls\nabc def") else {
+            panic!("abc is not a size");
+        };
+        assert!(error.to_string().starts_with("line 3: "));
     }
 }
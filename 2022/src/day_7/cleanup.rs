@@ -0,0 +1,464 @@
+//! Freeing disk space by deleting as little as possible.
+//!
+//! Part 2 deletes a single directory. [`plan`] may delete any number of directories and files and
+//! looks for the combination with the smallest total size that still frees enough space. Deleting
+//! a directory deletes exactly its files, so the search picks files: a knapsack problem solved by
+//! branch and bound over the file sizes, largest first. Files of equal size are interchangeable
+//! and are decided together.
+//!
+//! Exclusion patterns (see [`Pattern`]) protect what they match: a protected directory is never
+//! deleted as a whole, but its contents may be unless they are protected as well, e.g. with
+//! `/a/**`. `/` protects the root and nothing else.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use indextree::NodeId;
+
+use super::{query::Pattern, Tree};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub disk: usize,
+    /// free space needed
+    pub required: usize,
+    /// patterns of paths that must not be deleted
+    pub exclude: Vec<String>,
+    /// how many steps the search may take before settling for the best plan so far
+    pub limit: usize,
+}
+
+impl Default for Options {
+    /// The disk and update of the puzzle, nothing excluded
+    fn default() -> Self {
+        Self {
+            disk: 70_000_000,
+            required: 30_000_000,
+            exclude: vec![],
+            limit: 1_000_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// even deleting everything that is not excluded frees too little
+    NotEnoughSpace { missing: usize, deletable: usize },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NotEnoughSpace { missing, deletable } => write!(
+                f,
+                "{missing} more bytes are needed but only {deletable} may be deleted"
+            ),
+        }
+    }
+}
+
+/// A directory or file to delete
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deletion {
+    pub path: String,
+    pub size: usize,
+    pub directory: bool,
+}
+
+impl Display for Deletion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let slash = if self.directory && self.path != "/" {
+            "/"
+        } else {
+            ""
+        };
+        write!(f, "{}\t{}{slash}", self.size, self.path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    pub disk: usize,
+    pub used: usize,
+    /// bytes that have to be deleted, 0 if there is enough space already
+    pub missing: usize,
+    /// directories where all files are deleted, as a whole, and the other deleted files, sorted by
+    /// path
+    pub deletions: Vec<Deletion>,
+    pub freed: usize,
+    /// the smallest single directory that frees enough, the answer to part 2
+    pub smallest_directory: Option<Deletion>,
+    /// no combination frees enough with less; false if the search hit its limit
+    pub optimal: bool,
+    pub exclude: Vec<String>,
+}
+
+/// Explains the choice
+impl Display for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let free = self.disk - self.used.min(self.disk);
+        writeln!(
+            f,
+            "{} of {} bytes used, {free} free, {} more needed",
+            self.used, self.disk, self.missing
+        )?;
+        if !self.exclude.is_empty() {
+            writeln!(f, "never deleting {}", self.exclude.join(", "))?;
+        }
+        if self.missing == 0 {
+            return writeln!(f, "nothing has to be deleted");
+        }
+        writeln!(
+            f,
+            "deleting {} frees {} bytes, {} more than needed",
+            self.deletions.len(),
+            self.freed,
+            self.freed - self.missing
+        )?;
+        for deletion in &self.deletions {
+            writeln!(f, "  {deletion}")?;
+        }
+        if self.optimal {
+            writeln!(f, "nothing smaller frees enough")?;
+        } else {
+            writeln!(
+                f,
+                "the search stopped early, something smaller may free enough"
+            )?;
+        }
+        match &self.smallest_directory {
+            Some(directory) if directory.size > self.freed => writeln!(
+                f,
+                "the smallest directory that frees enough would delete {} bytes more: {}",
+                directory.size - self.freed,
+                directory.path
+            ),
+            Some(_) => writeln!(f, "no combination beats deleting a single directory"),
+            None => writeln!(f, "no single directory frees enough"),
+        }
+    }
+}
+
+/// Branch and bound over groups of equally large files
+struct Search {
+    /// size and number of files, largest first
+    groups: Vec<(usize, usize)>,
+    /// total size of the groups from an index on
+    remaining: Vec<usize>,
+    missing: usize,
+    taken: Vec<usize>,
+    best: usize,
+    best_taken: Option<Vec<usize>>,
+    seen: HashSet<(usize, usize)>,
+    steps: usize,
+    limit: usize,
+}
+
+impl Search {
+    fn run(&mut self, group: usize, total: usize) {
+        if total >= self.missing {
+            if total < self.best {
+                self.best = total;
+                self.best_taken = Some(self.taken.clone());
+            }
+            return;
+        }
+        // nothing can beat an exact fit, and a state seen before was already explored
+        if self.best == self.missing
+            || group == self.groups.len()
+            || total + self.remaining[group] < self.missing
+            || self.steps >= self.limit
+            || !self.seen.insert((group, total))
+        {
+            return;
+        }
+        self.steps += 1;
+
+        let (size, count) = self.groups[group];
+        for taken in (0..=count).rev() {
+            let next = total + taken * size;
+            if next < self.best {
+                self.taken[group] = taken;
+                self.run(group + 1, next);
+            }
+        }
+        self.taken[group] = 0;
+    }
+}
+
+/// A flag for every node, computed from the flags of its children
+fn bottom_up(
+    tree: &Tree,
+    mut flag: impl FnMut(NodeId, &HashMap<NodeId, bool>) -> bool,
+) -> HashMap<NodeId, bool> {
+    let mut flags = HashMap::new();
+    let nodes = tree.descendants(tree.root()).collect::<Vec<NodeId>>();
+    for id in nodes.into_iter().rev() {
+        let value = flag(id, &flags);
+        flags.insert(id, value);
+    }
+    flags
+}
+
+/// The topmost directories whose files are all `chosen` and may go as a whole, and the chosen
+/// files outside of them, sorted by path
+fn collapse(tree: &Tree, whole: &HashMap<NodeId, bool>, chosen: &HashSet<NodeId>) -> Vec<Deletion> {
+    let all_chosen = bottom_up(tree, |id, all_chosen| {
+        if tree.get(id).is_file() {
+            chosen.contains(&id)
+        } else {
+            whole[&id]
+                && tree.children(id).next().is_some()
+                && tree.children(id).all(|child| all_chosen[&child])
+        }
+    });
+    let mut deletions = tree
+        .descendants(tree.root())
+        .filter(|&id| all_chosen[&id] && tree.parent(id).is_none_or(|parent| !all_chosen[&parent]))
+        .map(|id| Deletion {
+            path: tree.path(id).to_string(),
+            size: tree.get_size(id),
+            directory: tree.get(id).is_directory(),
+        })
+        .collect::<Vec<Deletion>>();
+    deletions.sort_by(|a, b| a.path.cmp(&b.path));
+    deletions
+}
+
+/// Finds the directories and files with the smallest total size that leave `options.required`
+/// bytes free on a disk of `options.disk` bytes, never deleting what `options.exclude` matches.
+///
+/// # Errors
+/// if deleting everything that is not excluded does not free enough
+pub fn plan(tree: &Tree, options: &Options) -> Result<Plan, Error> {
+    let patterns = options
+        .exclude
+        .iter()
+        .map(|pattern| Pattern::new(pattern))
+        .collect::<Vec<Pattern>>();
+    let used = tree.get_size(tree.root());
+    let missing = used
+        .saturating_add(options.required)
+        .saturating_sub(options.disk);
+
+    // a directory can only go as a whole if nothing below it is excluded
+    let whole = bottom_up(tree, |id, whole| {
+        !patterns
+            .iter()
+            .any(|pattern| pattern.matches(tree.path(id)))
+            && tree.children(id).all(|child| whole[&child])
+    });
+    let files = tree
+        .descendants(tree.root())
+        .filter(|&id| tree.get(id).is_file() && whole[&id])
+        .collect::<Vec<NodeId>>();
+    let deletable = files.iter().map(|&id| tree.get_size(id)).sum::<usize>();
+    if deletable < missing {
+        return Err(Error::NotEnoughSpace { missing, deletable });
+    }
+    let smallest_directory = tree
+        .directories()
+        .filter(|&id| whole[&id] && tree.get_size(id) >= missing)
+        .min_by_key(|&id| tree.get_size(id));
+
+    let mut by_size = HashMap::<usize, Vec<NodeId>>::new();
+    for &id in &files {
+        by_size.entry(tree.get_size(id)).or_default().push(id);
+    }
+    let mut groups = by_size
+        .iter()
+        .map(|(&size, ids)| (size, ids.len()))
+        .collect::<Vec<(usize, usize)>>();
+    groups.sort_unstable_by(|a, b| b.cmp(a));
+    let mut remaining = vec![0; groups.len() + 1];
+    for (index, &(size, count)) in groups.iter().enumerate().rev() {
+        remaining[index] = remaining[index + 1] + size * count;
+    }
+
+    let mut search = Search {
+        taken: vec![0; groups.len()],
+        groups,
+        remaining,
+        missing,
+        // deleting the smallest directory, or everything, is a plan to beat
+        best: smallest_directory.map_or(deletable, |id| tree.get_size(id)),
+        best_taken: None,
+        seen: HashSet::new(),
+        steps: 0,
+        limit: options.limit,
+    };
+    search.run(0, 0);
+
+    let chosen = match (&search.best_taken, smallest_directory) {
+        // an empty directory also frees enough, but deleting nothing is smaller still
+        _ if missing == 0 => HashSet::new(),
+        (Some(taken), _) => {
+            // equally large files are interchangeable, take them in tree order
+            let mut chosen = HashSet::new();
+            for (&(size, _), &count) in search.groups.iter().zip(taken) {
+                chosen.extend(by_size[&size].iter().take(count));
+            }
+            chosen
+        }
+        (None, Some(directory)) => tree
+            .descendants(directory)
+            .filter(|&id| tree.get(id).is_file())
+            .collect(),
+        (None, None) => files.into_iter().collect(),
+    };
+    let deletions = collapse(tree, &whole, &chosen);
+
+    Ok(Plan {
+        disk: options.disk,
+        used,
+        missing,
+        freed: deletions.iter().map(|deletion| deletion.size).sum(),
+        deletions,
+        smallest_directory: smallest_directory.map(|id| Deletion {
+            path: tree.path(id).to_string(),
+            size: tree.get_size(id),
+            directory: true,
+        }),
+        optimal: search.steps < search.limit,
+        exclude: options.exclude.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plan, Error, Options};
+    use crate::day_7::{
+        generate_file_structure, smallest_directory_to_delete, tests::EXAMPLE_INPUT,
+    };
+
+    const INPUT: &str = include_str!("../../puzzle_input/day_7.txt");
+
+    fn paths(plan: &super::Plan) -> Vec<&str> {
+        plan.deletions
+            .iter()
+            .map(|deletion| deletion.path.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn example() {
+        let tree = generate_file_structure(EXAMPLE_INPUT).unwrap();
+
+        // 8381165 bytes are missing and c.dat is barely larger than that
+        let best = plan(&tree, &Options::default()).unwrap();
+        assert_eq!(best.missing, 8_381_165);
+        assert_eq!(paths(&best), ["/c.dat"]);
+        assert_eq!(best.freed, 8_504_156);
+        assert!(best.optimal);
+        assert_eq!(best.smallest_directory.as_ref().unwrap().size, 24_933_642);
+
+        let explanation = best.to_string();
+        assert!(explanation
+            .starts_with("48381165 of 70000000 bytes used, 21618835 free, 8381165 more needed\n"));
+        assert!(explanation.contains("deleting 1 frees 8504156 bytes, 122991 more than needed\n"));
+        assert!(explanation.contains("  8504156\t/c.dat\n"));
+        assert!(explanation.contains("nothing smaller frees enough\n"));
+        assert!(explanation.contains("would delete 16429486 bytes more: /d\n"));
+
+        // without c.dat two files of /d beat all of /d
+        let options = Options {
+            exclude: vec!["/c.dat".to_string()],
+            ..Options::default()
+        };
+        let best = plan(&tree, &options).unwrap();
+        assert_eq!(paths(&best), ["/d/d.ext", "/d/j"]);
+        assert_eq!(best.freed, 5_626_152 + 4_060_174);
+
+        // nothing beats a directory that fits exactly
+        let options = Options {
+            required: 21_618_835 + 94853,
+            ..Options::default()
+        };
+        let best = plan(&tree, &options).unwrap();
+        assert_eq!(paths(&best), ["/a"]);
+        assert!(best
+            .to_string()
+            .contains("no combination beats deleting a single directory\n"));
+    }
+
+    #[test]
+    fn exclusions() {
+        let tree = generate_file_structure(EXAMPLE_INPUT).unwrap();
+
+        let options = Options {
+            exclude: vec!["/".to_string(), "/c.dat".to_string(), "/d/**".to_string()],
+            ..Options::default()
+        };
+        let best = plan(&tree, &options).unwrap();
+        assert_eq!(paths(&best), ["/b.txt"]);
+        assert_eq!(best.smallest_directory, None);
+        assert!(best
+            .to_string()
+            .contains("never deleting /, /c.dat, /d/**\n"));
+
+        // the root may not go as a whole, but everything in it may
+        let options = Options {
+            required: 70_000_000,
+            exclude: vec!["/".to_string()],
+            ..Options::default()
+        };
+        let everything = plan(&tree, &options).unwrap();
+        assert_eq!(paths(&everything), ["/a", "/b.txt", "/c.dat", "/d"]);
+
+        let options = Options {
+            exclude: vec!["**/*.*".to_string(), "/d/k".to_string()],
+            ..Options::default()
+        };
+        assert_eq!(
+            plan(&tree, &options),
+            Err(Error::NotEnoughSpace {
+                missing: 8_381_165,
+                deletable: 29116 + 2557 + 584 + 4_060_174
+            })
+        );
+
+        let options = Options {
+            required: 0,
+            ..Options::default()
+        };
+        let nothing = plan(&tree, &options).unwrap();
+        assert!(nothing.deletions.is_empty());
+        assert!(nothing.to_string().ends_with("nothing has to be deleted\n"));
+
+        // empty files and directories tie with deleting nothing
+        let empty = generate_file_structure("$ cd /\n$ ls\ndir a\n$ cd a\n$ ls\n0 x").unwrap();
+        let options = Options {
+            disk: 100,
+            required: 0,
+            ..Options::default()
+        };
+        let nothing = plan(&empty, &options).unwrap();
+        assert_eq!(nothing.missing, 0);
+        assert_eq!(nothing.deletions, []);
+        assert_eq!(nothing.freed, 0);
+
+        let options = Options {
+            required: usize::MAX,
+            ..Options::default()
+        };
+        assert!(matches!(
+            plan(&tree, &options),
+            Err(Error::NotEnoughSpace { missing, .. }) if missing == usize::MAX - 70_000_000
+        ));
+    }
+
+    #[test]
+    fn problem() {
+        let tree = generate_file_structure(INPUT).unwrap();
+        let best = plan(&tree, &Options::default()).unwrap();
+        // many small deletions add up to exactly what is missing
+        assert_eq!(best.freed, best.missing);
+        assert!(best.optimal);
+        assert_eq!(
+            best.smallest_directory.unwrap().size,
+            smallest_directory_to_delete(INPUT)
+        );
+    }
+}
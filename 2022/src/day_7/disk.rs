@@ -0,0 +1,207 @@
+//! Moving file systems between a [`Tree`] and a real disk.
+//!
+//! [`materialize`] writes a tree below a directory, with sparse files so the recorded sizes cost
+//! next to no space. [`scan`] goes the other way and records a directory as a transcript of
+//! `cd` and `ls` that [`generate_file_structure`](super::generate_file_structure) reads back.
+
+use std::{
+    fmt::Write,
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use indextree::NodeId;
+
+use super::{is_name, quote, FileNode, Tree};
+
+/// Creates every directory and file of `tree` below `root`, which is created as well. Files are
+/// sparse and as long as their recorded size, existing files are truncated first. Nothing is
+/// written unless every name is a plain name that stays below `root`.
+///
+/// # Errors
+/// if a name contains `/` or NUL, is empty, `.` or `..`, or a directory or file can not be
+/// created
+pub fn materialize(tree: &Tree, root: &Path) -> io::Result<()> {
+    fn write(tree: &Tree, id: NodeId, path: &Path) -> io::Result<()> {
+        match tree.get(id) {
+            FileNode::File(file) => {
+                let handle = fs::File::create(path)?;
+                handle.set_len(file.size as u64)
+            }
+            FileNode::Directory(_) => {
+                fs::create_dir_all(path)?;
+                for child in tree.children(id) {
+                    write(tree, child, &path.join(tree.get(child).get_name()))?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    if let Some(id) = tree
+        .descendants(tree.root())
+        .skip(1)
+        .find(|&id| !is_name(tree.get(id).get_name()))
+    {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("{:?} is not a plain name", tree.get(id).get_name()),
+        ));
+    }
+    write(tree, tree.root(), root)
+}
+
+/// Name of a directory entry that a transcript can hold
+fn entry_name(entry: &fs::DirEntry) -> io::Result<String> {
+    let name = entry.file_name().into_string().map_err(|name| {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            format!("{} is not UTF-8", name.to_string_lossy()),
+        )
+    })?;
+    if name.contains(['\n', '\r']) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("{name:?} contains a line break"),
+        ));
+    }
+    Ok(name)
+}
+
+/// Transcript that lists `root` and every directory below it, siblings sorted by name. Sizes are
+/// the apparent sizes of the files. Symbolic links, pipes, sockets and devices are left out.
+///
+/// # Errors
+/// if a directory can not be read or a name is not UTF-8 or contains a line break
+pub fn scan(root: &Path) -> io::Result<String> {
+    fn list(path: &Path, transcript: &mut String) -> io::Result<()> {
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| {
+                let entry = entry?;
+                Ok((entry_name(&entry)?, entry.metadata()?))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.retain(|(_, metadata)| metadata.is_dir() || metadata.is_file());
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        transcript.push_str("$ ls\n");
+        for (name, metadata) in &entries {
+            if metadata.is_dir() {
+                let _ = writeln!(transcript, "dir {}", quote(name));
+            } else {
+                let _ = writeln!(transcript, "{} {}", metadata.len(), quote(name));
+            }
+        }
+        for (name, metadata) in &entries {
+            if metadata.is_dir() {
+                // `cd` would read a name starting with `-` as a flag
+                let end_of_flags = if name.starts_with('-') { "-- " } else { "" };
+                let _ = writeln!(transcript, "$ cd {end_of_flags}{}", quote(name));
+                list(&path.join(name), transcript)?;
+                transcript.push_str("$ cd ..\n");
+            }
+        }
+        Ok(())
+    }
+
+    let mut transcript = String::from("$ cd /\n");
+    list(root, &mut transcript)?;
+    Ok(transcript)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{materialize, scan};
+    use crate::{
+        day_7::{generate_file_structure, report::TreeView, tests::EXAMPLE_INPUT},
+        output::Format,
+    };
+    use std::{fs, path::PathBuf};
+
+    /// Empty directory for one test, removed when dropped
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "advent-of-code-2022-day-7-{name}-{}",
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let scratch = Scratch::new("round-trip");
+        let tree = generate_file_structure(EXAMPLE_INPUT).unwrap();
+        materialize(&tree, &scratch.0).unwrap();
+
+        let h = fs::metadata(scratch.0.join("a/h.lst")).unwrap();
+        assert_eq!(h.len(), 62596);
+        assert!(scratch.0.join("a/e").is_dir());
+
+        let transcript = scan(&scratch.0).unwrap();
+        assert!(transcript.starts_with("$ cd /\n$ ls\ndir a\n14848514 b.txt\n"));
+        let scanned = generate_file_structure(&transcript).unwrap();
+        assert_eq!(
+            TreeView::new(&scanned, None).render(Format::Text),
+            TreeView::new(&tree, None).render(Format::Text)
+        );
+    }
+
+    #[test]
+    fn awkward_names() {
+        let scratch = Scratch::new("awkward-names");
+        let tree = generate_file_structure(
+            "$ cd /\n$ ls\ndir 'my docs'\ndir -x\n$ cd -- -x\n$ ls\n7 -y\n$ cd '/my docs'\n$ ls\n12 'it'\\''s here.txt'\n3 'a\\b'",
+        )
+        .unwrap();
+        materialize(&tree, &scratch.0).unwrap();
+        assert_eq!(
+            fs::metadata(scratch.0.join("my docs/it's here.txt"))
+                .unwrap()
+                .len(),
+            12
+        );
+
+        let transcript = scan(&scratch.0).unwrap();
+        assert_eq!(
+            transcript,
+            "$ cd /\n$ ls\ndir -x\ndir 'my docs'\n$ cd -- -x\n$ ls\n7 -y\n$ cd ..\n$ cd 'my docs'\n$ ls\n3 'a\\b'\n12 'it'\\''s here.txt'\n$ cd ..\n"
+        );
+        let scanned = generate_file_structure(&transcript).unwrap();
+        assert_eq!(scanned.get_size(scanned.find("/my docs/a\\b").unwrap()), 3);
+        assert_eq!(scanned.get_size(scanned.find("/-x/-y").unwrap()), 7);
+        assert_eq!(scanned.get_size(scanned.root()), 22);
+    }
+
+    #[test]
+    fn escaping_names() {
+        let scratch = Scratch::new("escaping-names");
+        let tree = generate_file_structure("$ cd /\n$ ls\n1 a\n5 '../x'").unwrap();
+        // `../x` would end up next to `root`, in the scratch directory
+        assert!(materialize(&tree, &scratch.0.join("root")).is_err());
+        assert!(!scratch.0.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn special_files() {
+        let scratch = Scratch::new("special-files");
+        fs::create_dir_all(&scratch.0).unwrap();
+        fs::write(scratch.0.join("file"), "abc").unwrap();
+        let _socket = std::os::unix::net::UnixListener::bind(scratch.0.join("socket")).unwrap();
+        std::os::unix::fs::symlink("file", scratch.0.join("link")).unwrap();
+
+        assert_eq!(scan(&scratch.0).unwrap(), "$ cd /\n$ ls\n3 file\n");
+    }
+}
@@ -0,0 +1,211 @@
+//! Searching a reconstructed file system by path and by size.
+//!
+//! Patterns are matched against absolute paths one component at a time: `*` stands for any part
+//! of a name, `?` for a single character and a `**` component for any number of directories,
+//! none included. A pattern that does not start with `/` is matched from the root as well, so
+//! `**/*.lst` and `/**/*.lst` find the same files.
+
+use indextree::NodeId;
+
+use super::Tree;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    components: Vec<String>,
+}
+
+impl Pattern {
+    #[must_use]
+    pub fn new(pattern: &str) -> Self {
+        let components = pattern
+            .split('/')
+            .filter(|component| !component.is_empty())
+            .map(str::to_string)
+            .collect();
+        Self { components }
+    }
+
+    /// Whether `path`, absolute or relative to the root, matches the whole pattern
+    #[must_use]
+    pub fn matches(&self, path: &str) -> bool {
+        let names = path
+            .split('/')
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<&str>>();
+        matches_components(&self.components, &names)
+    }
+}
+
+/// Whether all of `names` match `pattern`. Elements of the pattern for which `is_star` holds stand
+/// for any number of names, every other element for one name that `matches` it. Only the last
+/// star is ever revisited: whatever an earlier star could still absorb, the last one can as well,
+/// so the match takes at most `pattern.len() * names.len()` steps.
+fn wildcard<P, N>(
+    pattern: &[P],
+    names: &[N],
+    is_star: impl Fn(&P) -> bool,
+    matches: impl Fn(&P, &N) -> bool,
+) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // the last star and the first name it does not absorb yet
+    let mut star = None;
+    while n < names.len() {
+        if p < pattern.len() && is_star(&pattern[p]) {
+            star = Some((p, n));
+            p += 1;
+        } else if p < pattern.len() && matches(&pattern[p], &names[n]) {
+            p += 1;
+            n += 1;
+        } else if let Some((star_p, star_n)) = star {
+            star = Some((star_p, star_n + 1));
+            p = star_p + 1;
+            n = star_n + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(is_star)
+}
+
+fn matches_components(pattern: &[String], names: &[&str]) -> bool {
+    wildcard(
+        pattern,
+        names,
+        |component| component == "**",
+        |component, name| matches_name(component, name),
+    )
+}
+
+fn matches_name(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let name = name.chars().collect::<Vec<char>>();
+    wildcard(
+        &pattern,
+        &name,
+        |&expected| expected == '*',
+        |&expected, &found| expected == '?' || expected == found,
+    )
+}
+
+/// Files and directories matching `pattern`, parents before their children
+#[must_use]
+pub fn glob(tree: &Tree, pattern: &str) -> Vec<NodeId> {
+    let pattern = Pattern::new(pattern);
+    tree.descendants(tree.root())
+        .filter(|&id| pattern.matches(tree.path(id)))
+        .collect()
+}
+
+/// Every file, largest first and equal sizes by path
+fn files_by_size(tree: &Tree) -> Vec<NodeId> {
+    let mut files = tree
+        .descendants(tree.root())
+        .filter(|&id| tree.get(id).is_file())
+        .collect::<Vec<NodeId>>();
+    files.sort_by(|&a, &b| {
+        tree.get_size(b)
+            .cmp(&tree.get_size(a))
+            .then_with(|| tree.path(a).cmp(tree.path(b)))
+    });
+    files
+}
+
+/// The `count` largest files, largest first
+#[must_use]
+pub fn largest_files(tree: &Tree, count: usize) -> Vec<NodeId> {
+    let mut files = files_by_size(tree);
+    files.truncate(count);
+    files
+}
+
+/// Files larger than `size`, largest first
+#[must_use]
+pub fn files_larger_than(tree: &Tree, size: usize) -> Vec<NodeId> {
+    let mut files = files_by_size(tree);
+    files.retain(|&id| tree.get_size(id) > size);
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{files_larger_than, glob, largest_files, Pattern};
+    use crate::day_7::{generate_file_structure, tests::EXAMPLE_INPUT, Tree};
+    use indextree::NodeId;
+
+    fn paths(tree: &Tree, ids: &[NodeId]) -> Vec<String> {
+        ids.iter().map(|&id| tree.path(id).to_string()).collect()
+    }
+
+    #[test]
+    fn patterns() {
+        let pattern = Pattern::new("**/*.lst");
+        assert!(pattern.matches("/a/h.lst"));
+        assert!(pattern.matches("h.lst"));
+        assert!(pattern.matches("/x/y/z/.lst"));
+        assert!(!pattern.matches("/a/h.lst/i"));
+        assert!(!pattern.matches("/a/h.lsts"));
+
+        assert!(Pattern::new("/a/?").matches("/a/f"));
+        assert!(!Pattern::new("/a/?").matches("/a/e/i"));
+        assert!(Pattern::new("a/**").matches("/a"));
+        assert!(Pattern::new("d/*.*").matches("/d/d.log"));
+        assert!(Pattern::new("").matches("/"));
+        assert!(Pattern::new("a*b*c").matches("/aXbYbZc"));
+        assert!(!Pattern::new("a*b*c").matches("/aXbYbZ"));
+        assert!(Pattern::new("/**/a/**/b").matches("/x/a/y/a/z/b"));
+    }
+
+    #[test]
+    fn pathological_patterns() {
+        // backtracking over every star would take exponential time here
+        let name = "a".repeat(1000);
+        assert!(!Pattern::new(&"*a".repeat(20)).matches(&format!("{name}b")));
+        assert!(!Pattern::new(&format!("{}b", "*a".repeat(20))).matches(&name));
+
+        let path = "/a".repeat(200);
+        assert!(!Pattern::new(&format!("{}/b", "/**/a".repeat(20))).matches(&path));
+    }
+
+    #[test]
+    fn lookup() {
+        let tree = generate_file_structure(EXAMPLE_INPUT).unwrap();
+
+        let i = tree.find("/a/e/i").unwrap();
+        assert_eq!(tree.get(i).get_file().path(), "/a/e/i");
+        assert_eq!(tree.get_size(i), 584);
+        assert_eq!(tree.find("/"), Some(tree.root()));
+        assert_eq!(tree.find("/a/e/../../d/"), tree.find("/d"));
+        assert_eq!(
+            tree.get(tree.find("/d").unwrap()).get_directory().path(),
+            "/d"
+        );
+        assert_eq!(tree.find("/a/x"), None);
+        assert_eq!(tree.find("/b.txt/x"), None);
+        assert_eq!(tree.find("a"), None);
+    }
+
+    #[test]
+    fn search() {
+        let tree = generate_file_structure(EXAMPLE_INPUT).unwrap();
+
+        assert_eq!(paths(&tree, &glob(&tree, "**/*.lst")), ["/a/h.lst"]);
+        assert_eq!(
+            paths(&tree, &glob(&tree, "/*/?")),
+            ["/a/e", "/a/f", "/a/g", "/d/j", "/d/k"]
+        );
+        assert_eq!(
+            paths(&tree, &glob(&tree, "**/d*")),
+            ["/d", "/d/d.log", "/d/d.ext"]
+        );
+
+        assert_eq!(
+            paths(&tree, &largest_files(&tree, 3)),
+            ["/b.txt", "/c.dat", "/d/d.log"]
+        );
+        assert_eq!(largest_files(&tree, 20).len(), 10);
+        assert_eq!(
+            paths(&tree, &files_larger_than(&tree, 7_214_296)),
+            ["/b.txt", "/c.dat", "/d/d.log"]
+        );
+    }
+}
@@ -0,0 +1,292 @@
+//! Disk usage of a reconstructed file system.
+//!
+//! [`DiskUsage`] lists directories with their recursive size the way `du` does, [`TreeView`]
+//! draws the whole hierarchy with file sizes the way `tree` does. Both render as plain text or
+//! JSON.
+
+use std::{collections::HashMap, fmt::Display};
+
+use indextree::NodeId;
+
+use super::{FileNode, Tree};
+use crate::output::{json_string, Format};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// parents before their children, siblings by name
+    #[default]
+    Path,
+    /// largest first, equal sizes by path
+    Size,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    /// deepest level to list, the root is at depth 0. Sizes always include everything below.
+    pub max_depth: Option<usize>,
+    pub order: Order,
+}
+
+/// Children of `id` sorted by name
+pub(super) fn sorted_children(tree: &Tree, id: NodeId) -> Vec<NodeId> {
+    let mut children = tree.children(id).collect::<Vec<NodeId>>();
+    children.sort_by(|a, b| tree.get(*a).get_name().cmp(tree.get(*b).get_name()));
+    children
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Usage {
+    pub path: String,
+    pub depth: usize,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskUsage {
+    pub entries: Vec<Usage>,
+}
+
+impl DiskUsage {
+    #[must_use]
+    pub fn new(tree: &Tree, options: &Options) -> Self {
+        fn visit(
+            tree: &Tree,
+            sizes: &HashMap<NodeId, usize>,
+            id: NodeId,
+            depth: usize,
+            options: &Options,
+            entries: &mut Vec<Usage>,
+        ) {
+            if options.max_depth.is_some_and(|max_depth| depth > max_depth) {
+                return;
+            }
+            entries.push(Usage {
+                path: tree.path(id).to_string(),
+                depth,
+                size: sizes[&id],
+            });
+            for child in sorted_children(tree, id) {
+                if tree.get(child).is_directory() {
+                    visit(tree, sizes, child, depth + 1, options, entries);
+                }
+            }
+        }
+
+        let mut entries = vec![];
+        visit(tree, &tree.sizes(), tree.root(), 0, options, &mut entries);
+        if options.order == Order::Size {
+            entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        }
+        Self { entries }
+    }
+
+    #[must_use]
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Text => self.to_string(),
+            Format::Json => {
+                let entries = self
+                    .entries
+                    .iter()
+                    .map(|usage| {
+                        format!(
+                            r#"{{"path":{},"depth":{},"size":{}}}"#,
+                            json_string(&usage.path),
+                            usage.depth,
+                            usage.size
+                        )
+                    })
+                    .collect::<Vec<String>>();
+                format!("[{}]", entries.join(","))
+            }
+        }
+    }
+}
+
+/// One directory per line, size and path separated by a tab
+impl Display for DiskUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for usage in &self.entries {
+            writeln!(f, "{}\t{}", usage.size, usage.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// The hierarchy below the root with sizes, siblings sorted by name
+pub struct TreeView<'a> {
+    tree: &'a Tree,
+    sizes: HashMap<NodeId, usize>,
+    max_depth: Option<usize>,
+}
+
+impl<'a> TreeView<'a> {
+    #[must_use]
+    pub fn new(tree: &'a Tree, max_depth: Option<usize>) -> Self {
+        Self {
+            tree,
+            sizes: tree.sizes(),
+            max_depth,
+        }
+    }
+
+    fn is_shown(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max_depth| depth <= max_depth)
+    }
+
+    /// Directories and files shown below the root
+    fn count(&self, id: NodeId, depth: usize) -> (usize, usize) {
+        let mut counts = (0, 0);
+        for child in self.tree.children(id) {
+            if !self.is_shown(depth + 1) {
+                break;
+            }
+            if self.tree.get(child).is_directory() {
+                let (directories, files) = self.count(child, depth + 1);
+                counts = (counts.0 + directories + 1, counts.1 + files);
+            } else {
+                counts.1 += 1;
+            }
+        }
+        counts
+    }
+
+    fn write_children(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        id: NodeId,
+        depth: usize,
+        prefix: &str,
+    ) -> std::fmt::Result {
+        if !self.is_shown(depth + 1) {
+            return Ok(());
+        }
+        let children = sorted_children(self.tree, id);
+        for (index, &child) in children.iter().enumerate() {
+            let last = index + 1 == children.len();
+            let (branch, indent) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            let node = self.tree.get(child);
+            let slash = if node.is_directory() { "/" } else { "" };
+            writeln!(
+                f,
+                "{prefix}{branch}[{}] {}{slash}",
+                self.sizes[&child],
+                node.get_name()
+            )?;
+            if node.is_directory() {
+                self.write_children(f, child, depth + 1, &format!("{prefix}{indent}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn to_json(&self, id: NodeId, depth: usize) -> String {
+        let node = self.tree.get(id);
+        let size = self.sizes[&id];
+        let name = json_string(node.get_name());
+        match node {
+            FileNode::File(_) => format!(r#"{{"name":{name},"type":"file","size":{size}}}"#),
+            FileNode::Directory(_) => {
+                let children = if self.is_shown(depth + 1) {
+                    sorted_children(self.tree, id)
+                        .into_iter()
+                        .map(|child| self.to_json(child, depth + 1))
+                        .collect::<Vec<String>>()
+                } else {
+                    vec![]
+                };
+                format!(
+                    r#"{{"name":{name},"type":"directory","size":{size},"children":[{}]}}"#,
+                    children.join(",")
+                )
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Text => self.to_string(),
+            Format::Json => self.to_json(self.tree.root(), 0),
+        }
+    }
+}
+
+impl Display for TreeView<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let root = self.tree.root();
+        writeln!(f, "[{}] /", self.sizes[&root])?;
+        self.write_children(f, root, 0, "")?;
+        let (directories, files) = self.count(root, 0);
+        writeln!(f)?;
+        writeln!(f, "{directories} directories, {files} files")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiskUsage, Options, Order, TreeView};
+    use crate::{
+        day_7::{generate_file_structure, tests::EXAMPLE_INPUT},
+        output::Format,
+    };
+
+    #[test]
+    fn disk_usage() {
+        let tree = generate_file_structure(EXAMPLE_INPUT).unwrap();
+
+        let usage = DiskUsage::new(&tree, &Options::default());
+        assert_eq!(
+            usage.render(Format::Text),
+            "48381165\t/\n94853\t/a\n584\t/a/e\n24933642\t/d\n"
+        );
+
+        let options = Options {
+            max_depth: Some(1),
+            order: Order::Size,
+        };
+        let usage = DiskUsage::new(&tree, &options);
+        assert_eq!(
+            usage.render(Format::Json),
+            r#"[{"path":"/","depth":0,"size":48381165},{"path":"/d","depth":1,"size":24933642},{"path":"/a","depth":1,"size":94853}]"#
+        );
+    }
+
+    #[test]
+    fn tree_view() {
+        let tree = generate_file_structure(EXAMPLE_INPUT).unwrap();
+
+        let expected = "\
+[48381165] /
+├── [94853] a/
+│   ├── [584] e/
+│   │   └── [584] i
+│   ├── [29116] f
+│   ├── [2557] g
+│   └── [62596] h.lst
+├── [14848514] b.txt
+├── [8504156] c.dat
+└── [24933642] d/
+    ├── [5626152] d.ext
+    ├── [8033020] d.log
+    ├── [4060174] j
+    └── [7214296] k
+
+3 directories, 10 files
+";
+        assert_eq!(TreeView::new(&tree, None).render(Format::Text), expected);
+
+        let shallow = TreeView::new(&tree, Some(1)).render(Format::Text);
+        assert!(shallow.ends_with("└── [24933642] d/\n\n2 directories, 2 files\n"));
+
+        let json = TreeView::new(&tree, Some(1)).render(Format::Json);
+        assert!(json.starts_with(
+            r#"{"name":"/","type":"directory","size":48381165,"children":[{"name":"a","type":"directory","size":94853,"children":[]},"#
+        ));
+        assert!(json.contains(r#"{"name":"b.txt","type":"file","size":14848514}"#));
+    }
+}
@@ -0,0 +1,717 @@
+//! Saving a reconstructed file system, loading it back and comparing two of them.
+//!
+//! A snapshot lists every directory and file with siblings sorted by name, so the same file
+//! system always saves to the same text however the transcript walked it. The text format has
+//! one entry per line, `dir <path>` or `<size> <path>` with the path running to the end of the
+//! line. The JSON format is the nested one of [`TreeView`]. [`diff`] compares two trees, for
+//! example two transcripts of the same disk taken a week apart.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{Display, Write},
+};
+
+use indextree::NodeId;
+
+use super::{
+    is_name,
+    report::{sorted_children, TreeView},
+    Directory, File, FileNode, Tree,
+};
+use crate::output::{json_string, Format};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// a line of the text format that is neither `dir <path>` nor `<size> <path>` with an
+    /// absolute path
+    InvalidLine { line: usize, content: String },
+    /// input that is not JSON at all
+    InvalidJson,
+    /// JSON that does not describe a directory tree, with what is wrong
+    UnexpectedJson(String),
+    /// path that is listed twice or lies below a file
+    Conflict(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidLine { line, content } => write!(
+                f,
+                "line {line}: expected `dir <path>` or `<size> <path>` but found {content:?}"
+            ),
+            Error::InvalidJson => write!(f, "the snapshot is not valid JSON"),
+            Error::UnexpectedJson(reason) => write!(f, "the snapshot is not a tree: {reason}"),
+            Error::Conflict(path) => write!(f, "{path} is listed twice or below a file"),
+        }
+    }
+}
+
+/// `tree` in `format`, see the [module documentation](self)
+#[must_use]
+pub fn save(tree: &Tree, format: Format) -> String {
+    fn write(tree: &Tree, id: NodeId, snapshot: &mut String) {
+        match tree.get(id) {
+            FileNode::File(file) => {
+                let _ = writeln!(snapshot, "{} {}", file.size, file.path());
+            }
+            FileNode::Directory(directory) => {
+                let _ = writeln!(snapshot, "dir {}", directory.path());
+                for child in sorted_children(tree, id) {
+                    write(tree, child, snapshot);
+                }
+            }
+        }
+    }
+
+    match format {
+        Format::Text => {
+            let mut snapshot = String::new();
+            write(tree, tree.root(), &mut snapshot);
+            snapshot
+        }
+        Format::Json => TreeView::new(tree, None).render(Format::Json),
+    }
+}
+
+/// Adds `node` below `parent` unless the parent already has a child of that name
+fn insert(tree: &mut Tree, parent: NodeId, node: FileNode) -> Result<NodeId, Error> {
+    if tree.find_child(parent, node.get_name()).is_some() {
+        let path = tree.path(parent).trim_end_matches('/');
+        return Err(Error::Conflict(format!("{path}/{}", node.get_name())));
+    }
+    Ok(tree.add_child(parent, node))
+}
+
+fn root() -> Tree {
+    Tree::new(Directory {
+        name: "/".to_string(),
+        path: "/".to_string(),
+    })
+}
+
+fn load_text(snapshot: &str) -> Result<Tree, Error> {
+    let mut tree = root();
+    // directories created for a path below them that no `dir` line has listed yet
+    let mut implied = HashSet::new();
+    for (index, content) in snapshot.lines().enumerate() {
+        let invalid = || Error::InvalidLine {
+            line: index + 1,
+            content: content.to_string(),
+        };
+        if content.is_empty() {
+            continue;
+        }
+        let (kind, path) = content.split_once(' ').ok_or_else(invalid)?;
+        let size = match kind {
+            "dir" => None,
+            size => Some(size.parse::<usize>().map_err(|_| invalid())?),
+        };
+        let names = match path.strip_prefix('/').ok_or_else(invalid)? {
+            "" => vec![],
+            relative => relative.split('/').collect::<Vec<&str>>(),
+        };
+        if !names.iter().all(|name| is_name(name)) {
+            return Err(invalid());
+        }
+        let Some((name, parents)) = names.split_last() else {
+            // the root, which is always a directory
+            if size.is_some() {
+                return Err(Error::Conflict("/".to_string()));
+            }
+            continue;
+        };
+
+        // parents missing from the snapshot are created like `mkdir -p` would
+        let mut parent = tree.root();
+        for &directory in parents {
+            parent = match tree.find_child(parent, directory) {
+                Some(id) if tree.get(id).is_directory() => id,
+                Some(id) => return Err(Error::Conflict(tree.path(id).to_string())),
+                None => {
+                    let id = tree.add_child(
+                        parent,
+                        FileNode::Directory(Directory::new(directory.to_string())),
+                    );
+                    implied.insert(id);
+                    id
+                }
+            };
+        }
+        // a directory listed after something below it is already there
+        if size.is_none()
+            && tree
+                .find_child(parent, name)
+                .is_some_and(|id| implied.remove(&id))
+        {
+            continue;
+        }
+        let node = match size {
+            Some(size) => FileNode::File(File::new(name.to_string(), size)),
+            None => FileNode::Directory(Directory::new(name.to_string())),
+        };
+        insert(&mut tree, parent, node)?;
+    }
+    Ok(tree)
+}
+
+fn load_json(snapshot: &str) -> Result<Tree, Error> {
+    use json::Value;
+
+    /// Member `key` of the object `value` describing `path`
+    fn member<'a>(value: &'a Value, key: &str, path: &str) -> Result<&'a Value, Error> {
+        match value {
+            Value::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value)
+                .ok_or_else(|| Error::UnexpectedJson(format!("{path} has no {key:?}"))),
+            _ => Err(Error::UnexpectedJson(format!("{path} is not an object"))),
+        }
+    }
+
+    /// Adds the children listed in the directory `value` below `parent`
+    fn children(tree: &mut Tree, parent: NodeId, value: &Value) -> Result<(), Error> {
+        let path = tree.path(parent).to_string();
+        let Value::Array(listed) = member(value, "children", &path)? else {
+            return Err(Error::UnexpectedJson(format!(
+                "the children of {path} are not an array"
+            )));
+        };
+        for child in listed {
+            let Value::String(name) = member(child, "name", &format!("a child of {path}"))? else {
+                return Err(Error::UnexpectedJson(format!(
+                    "a child of {path} has a name that is not a string"
+                )));
+            };
+            if !is_name(name) {
+                return Err(Error::UnexpectedJson(format!(
+                    "a child of {path} is called {name:?}"
+                )));
+            }
+            let child_path = format!("{}/{name}", path.trim_end_matches('/'));
+            match member(child, "type", &child_path)? {
+                Value::String(kind) if kind == "directory" => {
+                    let id = insert(
+                        tree,
+                        parent,
+                        FileNode::Directory(Directory::new(name.clone())),
+                    )?;
+                    children(tree, id, child)?;
+                }
+                Value::String(kind) if kind == "file" => {
+                    let size = match member(child, "size", &child_path)? {
+                        Value::Number(size) => size.parse::<usize>().ok(),
+                        _ => None,
+                    }
+                    .ok_or_else(|| {
+                        Error::UnexpectedJson(format!("{child_path} has no valid size"))
+                    })?;
+                    insert(tree, parent, FileNode::File(File::new(name.clone(), size)))?;
+                }
+                _ => {
+                    return Err(Error::UnexpectedJson(format!(
+                        "{child_path} is neither a file nor a directory"
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    let value = json::parse(snapshot).ok_or(Error::InvalidJson)?;
+    match member(&value, "type", "the root")? {
+        Value::String(kind) if kind == "directory" => {}
+        _ => {
+            return Err(Error::UnexpectedJson(
+                "the root is not a directory".to_string(),
+            ))
+        }
+    }
+    let mut tree = root();
+    let id = tree.root();
+    children(&mut tree, id, &value)?;
+    Ok(tree)
+}
+
+/// Reads back what [`save`] wrote in `format`. Sizes of directories are not read, they always
+/// follow from the files.
+///
+/// # Errors
+/// if the snapshot can not be read or lists a path twice or below a file
+pub fn load(snapshot: &str, format: Format) -> Result<Tree, Error> {
+    match format {
+        Format::Text => load_text(snapshot),
+        Format::Json => load_json(snapshot),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    Resized,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Added => write!(f, "added"),
+            Change::Removed => write!(f, "removed"),
+            Change::Resized => write!(f, "resized"),
+        }
+    }
+}
+
+/// A file or directory that is not the same in both trees. Sizes of directories include
+/// everything below them, a side that does not have the path counts as size 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    pub path: String,
+    pub directory: bool,
+    pub change: Change,
+    pub before: usize,
+    pub after: usize,
+}
+
+impl Difference {
+    /// Growth in bytes, negative if the path shrank or was removed
+    #[must_use]
+    pub fn delta(&self) -> i128 {
+        self.after as i128 - self.before as i128
+    }
+}
+
+/// Change in size, path and kind of change separated by tabs
+impl Display for Difference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let slash = if self.directory && self.path != "/" {
+            "/"
+        } else {
+            ""
+        };
+        write!(
+            f,
+            "{:+}\t{}\t{}{slash}",
+            self.delta(),
+            self.change,
+            self.path
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diff {
+    pub differences: Vec<Difference>,
+}
+
+impl Diff {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    #[must_use]
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Text => self.to_string(),
+            Format::Json => {
+                let differences = self
+                    .differences
+                    .iter()
+                    .map(|difference| {
+                        let kind = if difference.directory {
+                            "directory"
+                        } else {
+                            "file"
+                        };
+                        format!(
+                            r#"{{"path":{},"type":"{kind}","change":"{}","before":{},"after":{},"delta":{}}}"#,
+                            json_string(&difference.path),
+                            difference.change,
+                            difference.before,
+                            difference.after,
+                            difference.delta()
+                        )
+                    })
+                    .collect::<Vec<String>>();
+                format!("[{}]", differences.join(","))
+            }
+        }
+    }
+}
+
+/// One difference per line
+impl Display for Diff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for difference in &self.differences {
+            writeln!(f, "{difference}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether each path is a directory and its size, by path components so that parents come
+/// right before their children
+fn entries(tree: &Tree) -> BTreeMap<Vec<&str>, (bool, usize)> {
+    tree.descendants(tree.root())
+        .map(|id| {
+            let components = tree
+                .path(id)
+                .split('/')
+                .filter(|name| !name.is_empty())
+                .collect();
+            (components, (tree.get(id).is_directory(), tree.get_size(id)))
+        })
+        .collect()
+}
+
+/// Every path added, removed or resized from `before` to `after`, parents before their
+/// children. Of a whole directory added or removed only the directory itself is listed. A file
+/// that became a directory, or the other way round, is removed and added again.
+#[must_use]
+pub fn diff(before: &Tree, after: &Tree) -> Diff {
+    let before = entries(before);
+    let after = entries(after);
+    let is_directory = |entries: &BTreeMap<Vec<&str>, (bool, usize)>, path: &[&str]| {
+        entries.get(path).is_some_and(|&(directory, _)| directory)
+    };
+    let difference = |path: &[&str], directory, change, before, after| Difference {
+        path: format!("/{}", path.join("/")),
+        directory,
+        change,
+        before,
+        after,
+    };
+
+    let mut paths = before.keys().chain(after.keys()).collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+
+    let mut differences = vec![];
+    for path in paths {
+        let parent = &path[..path.len().saturating_sub(1)];
+        match (before.get(path), after.get(path)) {
+            (Some(&(was_directory, was)), Some(&(directory, size)))
+                if was_directory == directory =>
+            {
+                if was != size {
+                    differences.push(difference(path, directory, Change::Resized, was, size));
+                }
+            }
+            (old, new) => {
+                if let Some(&(directory, size)) = old {
+                    if is_directory(&after, parent) {
+                        differences.push(difference(path, directory, Change::Removed, size, 0));
+                    }
+                }
+                if let Some(&(directory, size)) = new {
+                    if is_directory(&before, parent) {
+                        differences.push(difference(path, directory, Change::Added, 0, size));
+                    }
+                }
+            }
+        }
+    }
+    Diff { differences }
+}
+
+/// Just enough JSON to read snapshots back
+mod json {
+    use nom::{
+        branch::alt,
+        bytes::complete::{tag, take_while_m_n},
+        character::complete::{char, digit1, multispace0, none_of, one_of},
+        combinator::{all_consuming, map, map_opt, opt, recognize, value},
+        multi::{fold_many0, separated_list0},
+        sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
+        IResult,
+    };
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Value {
+        Null,
+        Bool(bool),
+        /// the number as written, JSON does not limit its size
+        Number(String),
+        String(String),
+        Array(Vec<Value>),
+        /// members in the order they are written
+        Object(Vec<(String, Value)>),
+    }
+
+    /// A character of a string, escapes of UTF-16 surrogates are not supported
+    fn character(input: &str) -> IResult<&str, char> {
+        alt((
+            preceded(
+                char('\\'),
+                alt((
+                    value('"', char('"')),
+                    value('\\', char('\\')),
+                    value('/', char('/')),
+                    value('\u{8}', char('b')),
+                    value('\u{c}', char('f')),
+                    value('\n', char('n')),
+                    value('\r', char('r')),
+                    value('\t', char('t')),
+                    map_opt(
+                        preceded(
+                            char('u'),
+                            take_while_m_n(4, 4, |c: char| c.is_ascii_hexdigit()),
+                        ),
+                        |hex| u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                    ),
+                )),
+            ),
+            none_of("\"\\"),
+        ))(input)
+    }
+
+    fn string(input: &str) -> IResult<&str, String> {
+        delimited(
+            char('"'),
+            fold_many0(character, String::new, |mut string, c| {
+                string.push(c);
+                string
+            }),
+            char('"'),
+        )(input)
+    }
+
+    fn number(input: &str) -> IResult<&str, &str> {
+        recognize(tuple((
+            opt(char('-')),
+            digit1,
+            opt(pair(char('.'), digit1)),
+            opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+        )))(input)
+    }
+
+    /// `token` with any whitespace before it
+    fn spaced<'a, T>(
+        token: impl FnMut(&'a str) -> IResult<&'a str, T>,
+    ) -> impl FnMut(&'a str) -> IResult<&'a str, T> {
+        preceded(multispace0, token)
+    }
+
+    fn json(input: &str) -> IResult<&str, Value> {
+        spaced(alt((
+            value(Value::Null, tag("null")),
+            value(Value::Bool(true), tag("true")),
+            value(Value::Bool(false), tag("false")),
+            map(number, |number| Value::Number(number.to_string())),
+            map(string, Value::String),
+            map(
+                delimited(
+                    char('['),
+                    separated_list0(spaced(char(',')), json),
+                    spaced(char(']')),
+                ),
+                Value::Array,
+            ),
+            map(
+                delimited(
+                    char('{'),
+                    separated_list0(
+                        spaced(char(',')),
+                        separated_pair(spaced(string), spaced(char(':')), json),
+                    ),
+                    spaced(char('}')),
+                ),
+                Value::Object,
+            ),
+        )))(input)
+    }
+
+    /// The single value `input` holds
+    pub fn parse(input: &str) -> Option<Value> {
+        all_consuming(terminated(json, multispace0))(input)
+            .ok()
+            .map(|(_, value)| value)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{parse, Value};
+
+        #[test]
+        fn values() {
+            assert_eq!(
+                parse(r#" {"a": [1, -2.5e3, "x\"é\n"], "b": {}, "c": [true, null]} "#),
+                Some(Value::Object(vec![
+                    (
+                        "a".to_string(),
+                        Value::Array(vec![
+                            Value::Number("1".to_string()),
+                            Value::Number("-2.5e3".to_string()),
+                            Value::String("x\"é\n".to_string()),
+                        ])
+                    ),
+                    ("b".to_string(), Value::Object(vec![])),
+                    (
+                        "c".to_string(),
+                        Value::Array(vec![Value::Bool(true), Value::Null])
+                    ),
+                ]))
+            );
+            assert_eq!(parse("[1,]"), None);
+            assert_eq!(parse(r#""\ud83d\ude00""#), None);
+            assert_eq!(parse("{} {}"), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, load, save, Change, Error};
+    use crate::{
+        day_7::{generate_file_structure, report::TreeView, tests::EXAMPLE_INPUT},
+        output::Format,
+    };
+
+    #[test]
+    fn round_trip() {
+        let tree = generate_file_structure(EXAMPLE_INPUT).unwrap();
+        let text = save(&tree, Format::Text);
+        assert_eq!(
+            text,
+            "dir /\n\
+             dir /a\n\
+             dir /a/e\n\
+             584 /a/e/i\n\
+             29116 /a/f\n\
+             2557 /a/g\n\
+             62596 /a/h.lst\n\
+             14848514 /b.txt\n\
+             8504156 /c.dat\n\
+             dir /d\n\
+             5626152 /d/d.ext\n\
+             8033020 /d/d.log\n\
+             4060174 /d/j\n\
+             7214296 /d/k\n"
+        );
+
+        for format in [Format::Text, Format::Json] {
+            let loaded = load(&save(&tree, format), format).unwrap();
+            assert_eq!(
+                TreeView::new(&loaded, None).render(Format::Text),
+                TreeView::new(&tree, None).render(Format::Text)
+            );
+            assert_eq!(save(&loaded, Format::Text), text);
+        }
+
+        let awkward = generate_file_structure(
+            "$ cd /\n$ ls\ndir 'my docs'\n$ cd 'my docs'\n$ ls\n12 'it'\\''s \"here\".txt'",
+        )
+        .unwrap();
+        for format in [Format::Text, Format::Json] {
+            let loaded = load(&save(&awkward, format), format).unwrap();
+            assert_eq!(
+                loaded.get_size(loaded.find("/my docs/it's \"here\".txt").unwrap()),
+                12
+            );
+        }
+    }
+
+    #[test]
+    fn loading() {
+        let tree = load("5 /x/y/z\n\n7 /x/w\n", Format::Text).unwrap();
+        assert!(tree.get(tree.find("/x/y").unwrap()).is_directory());
+        assert_eq!(tree.get_size(tree.root()), 12);
+
+        assert_eq!(
+            load("dir /\n12 a\n", Format::Text).unwrap_err(),
+            Error::InvalidLine {
+                line: 2,
+                content: "12 a".to_string()
+            }
+        );
+        assert!(matches!(
+            load("dir /\nx /a\n", Format::Text),
+            Err(Error::InvalidLine { line: 2, .. })
+        ));
+        assert!(matches!(
+            load("dir /a/../b\n", Format::Text),
+            Err(Error::InvalidLine { line: 1, .. })
+        ));
+        assert_eq!(
+            load("dir /a\n3 /a\n", Format::Text).unwrap_err(),
+            Error::Conflict("/a".to_string())
+        );
+        assert_eq!(
+            load("3 /a\n3 /a/b\n", Format::Text).unwrap_err(),
+            Error::Conflict("/a".to_string())
+        );
+        assert!(matches!(
+            load("dir /a//b\n", Format::Text),
+            Err(Error::InvalidLine { line: 1, .. })
+        ));
+        assert!(matches!(
+            load("3 /a/\n", Format::Text),
+            Err(Error::InvalidLine { line: 1, .. })
+        ));
+
+        // the same tree with a directory listed after its contents
+        let later = load("dir /\n1 /a/x\ndir /a\n2 /a/b/y\ndir /a/b\n", Format::Text).unwrap();
+        assert_eq!(
+            save(&later, Format::Text),
+            "dir /\ndir /a\ndir /a/b\n2 /a/b/y\n1 /a/x\n"
+        );
+        assert_eq!(
+            load("1 /a/x\ndir /a\ndir /a\n", Format::Text).unwrap_err(),
+            Error::Conflict("/a".to_string())
+        );
+        assert_eq!(
+            load("1 /a/x\n3 /a\n", Format::Text).unwrap_err(),
+            Error::Conflict("/a".to_string())
+        );
+
+        assert_eq!(load("{", Format::Json).unwrap_err(), Error::InvalidJson);
+        assert!(matches!(
+            load(
+                r#"{"type":"directory","children":[{"name":"a","type":"file"}]}"#,
+                Format::Json
+            ),
+            Err(Error::UnexpectedJson(_))
+        ));
+        assert!(matches!(
+            load(r#"{"type":"file","size":3}"#, Format::Json),
+            Err(Error::UnexpectedJson(_))
+        ));
+        let tree = load(
+            r#"{"type":"directory","children":[{"name":"a","type":"directory","children":[{"name":"b","type":"file","size":3}]}]}"#,
+            Format::Json,
+        )
+        .unwrap();
+        assert_eq!(tree.get_size(tree.find("/a/b").unwrap()), 3);
+    }
+
+    #[test]
+    fn differences() {
+        let before = generate_file_structure(EXAMPLE_INPUT).unwrap();
+        assert!(diff(&before, &before).is_empty());
+
+        let after = generate_file_structure(&format!(
+            "{EXAMPLE_INPUT}\n$ cd /\n$ rm -r d c.dat\n$ mkdir -p a/e/x c.dat\n$ ls a/e\n1000 i\ndir x\n$ ls a/e/x\n5 y"
+        ))
+        .unwrap();
+        let differences = diff(&before, &after);
+        assert_eq!(
+            differences.to_string(),
+            "-33437377\tresized\t/\n\
+             +421\tresized\t/a/\n\
+             +421\tresized\t/a/e/\n\
+             +416\tresized\t/a/e/i\n\
+             +5\tadded\t/a/e/x/\n\
+             -8504156\tremoved\t/c.dat\n\
+             +0\tadded\t/c.dat/\n\
+             -24933642\tremoved\t/d/\n"
+        );
+        assert_eq!(differences.differences[1].change, Change::Resized);
+        assert_eq!(differences.differences[1].delta(), 421);
+        assert_eq!(differences.differences[7].delta(), -24_933_642);
+        assert!(differences.render(Format::Json).starts_with(
+            r#"[{"path":"/","type":"directory","change":"resized","before":48381165,"after":14943788,"delta":-33437377},"#
+        ));
+    }
+}
@@ -0,0 +1,637 @@
+//! Replaying a terminal transcript and checking that it agrees with itself.
+//!
+//! A real transcript can list a directory twice with different contents, report a file with two
+//! sizes or `cd` into a directory no listing has shown. [`replay`] finds every such
+//! contradiction and either refuses the transcript or merges what it saw, depending on [`Merge`].
+
+use std::{collections::HashSet, fmt::Display};
+
+use indextree::NodeId;
+
+use super::{listing_entry, ChangeDir, Command, Directory, Error, File, FileNode, Line, Tree};
+
+/// How repeated `ls` output of a directory is combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Merge {
+    /// any contradiction is an error
+    Strict,
+    /// everything listed is kept and the size listed last wins
+    Lenient,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// `cd` into a directory that no listing of its parent has shown
+    UnlistedDirectory { line: usize, path: String },
+    /// `ls` of a directory shows other entries than the previous `ls` of it
+    ChangedListing {
+        line: usize,
+        path: String,
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    /// a file is listed with another size than before
+    ChangedSize {
+        line: usize,
+        path: String,
+        before: usize,
+        after: usize,
+    },
+    /// a name is listed as a file and as a directory, the first one is kept
+    ChangedKind { line: usize, path: String },
+    /// `cd` or `ls` through a path that is a file
+    NotADirectory { line: usize, path: String },
+    /// `pwd` prints another directory than the one `cd` went to
+    WrongDirectory {
+        line: usize,
+        expected: String,
+        found: String,
+    },
+    /// `cd` or `ls` that can not be read, e.g. `cd` without a directory. It is skipped together
+    /// with its output.
+    MalformedCommand { line: usize, command: String },
+}
+
+impl Inconsistency {
+    /// 1-based line of the transcript
+    #[must_use]
+    pub fn line(&self) -> usize {
+        match self {
+            Inconsistency::UnlistedDirectory { line, .. }
+            | Inconsistency::ChangedListing { line, .. }
+            | Inconsistency::ChangedSize { line, .. }
+            | Inconsistency::ChangedKind { line, .. }
+            | Inconsistency::NotADirectory { line, .. }
+            | Inconsistency::WrongDirectory { line, .. }
+            | Inconsistency::MalformedCommand { line, .. } => *line,
+        }
+    }
+}
+
+impl Display for Inconsistency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Inconsistency::UnlistedDirectory { line, path } => {
+                write!(f, "line {line}: {path} was never listed")
+            }
+            Inconsistency::ChangedListing {
+                line,
+                path,
+                added,
+                removed,
+            } => {
+                write!(f, "line {line}: {path} was listed before")?;
+                if !added.is_empty() {
+                    write!(f, ", now with {}", added.join(", "))?;
+                }
+                if !removed.is_empty() {
+                    write!(f, ", now without {}", removed.join(", "))?;
+                }
+                Ok(())
+            }
+            Inconsistency::ChangedSize {
+                line,
+                path,
+                before,
+                after,
+            } => write!(f, "line {line}: {path} was {before} bytes, now {after}"),
+            Inconsistency::ChangedKind { line, path } => {
+                write!(f, "line {line}: {path} is listed as a file and a directory")
+            }
+            Inconsistency::NotADirectory { line, path } => {
+                write!(f, "line {line}: {path} is a file, not a directory")
+            }
+            Inconsistency::WrongDirectory {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line}: working directory is {found}, not {expected}"
+            ),
+            Inconsistency::MalformedCommand { line, command } => {
+                write!(f, "line {line}: can not follow {command:?}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub tree: Tree,
+    /// in the order of the transcript; always empty after a strict replay
+    pub inconsistencies: Vec<Inconsistency>,
+}
+
+/// The `ls` whose output is being read
+struct Listing {
+    directory: NodeId,
+    line: usize,
+    /// the directory was listed before
+    repeated: bool,
+    names: HashSet<String>,
+    added: Vec<String>,
+}
+
+/// What the lines after the last command are
+enum Output {
+    /// entries of a directory
+    Listing(Listing),
+    /// the path `pwd` prints
+    WorkingDirectory,
+    /// anything else, e.g. error messages
+    Ignored,
+}
+
+/// What to do about directories on a path that do not exist
+#[derive(Clone, Copy)]
+enum Missing {
+    /// the path leads nowhere
+    Fail,
+    /// create them, like `mkdir -p`
+    Create,
+    /// create them and report the 1-based line that went there
+    Report(usize),
+}
+
+struct Replayer {
+    tree: Tree,
+    merge: Merge,
+    listed: HashSet<NodeId>,
+    output: Output,
+    inconsistencies: Vec<Inconsistency>,
+}
+
+impl Replayer {
+    /// Directory at the end of `steps` from the working directory. Stops at files.
+    fn follow(&mut self, steps: &[ChangeDir], missing: Missing) -> Option<NodeId> {
+        let mut directory = self.tree.current_working_directory;
+        for step in steps {
+            directory = match step {
+                ChangeDir::Root => self.tree.root,
+                ChangeDir::Up => self.tree.parent(directory).unwrap_or(self.tree.root),
+                ChangeDir::Dir(name) => match (self.tree.find_child(directory, name), missing) {
+                    (Some(child), _) if self.tree.get(child).is_directory() => child,
+                    (Some(file), Missing::Report(line)) => {
+                        let path = self.tree.path(file).to_string();
+                        self.inconsistencies
+                            .push(Inconsistency::NotADirectory { line, path });
+                        return None;
+                    }
+                    (Some(_), _) | (None, Missing::Fail) => return None,
+                    (None, Missing::Create) => self
+                        .tree
+                        .add_child(directory, FileNode::Directory(Directory::new(name.clone()))),
+                    (None, Missing::Report(line)) => {
+                        // the shell went there, so the directory exists
+                        let child = self.tree.add_child(
+                            directory,
+                            FileNode::Directory(Directory::new(name.clone())),
+                        );
+                        let path = self.tree.path(child).to_string();
+                        self.inconsistencies
+                            .push(Inconsistency::UnlistedDirectory { line, path });
+                        child
+                    }
+                },
+            };
+        }
+        Some(directory)
+    }
+
+    /// Parent directory and name of the last component of `path`
+    fn split(&mut self, path: &str, missing: Missing) -> Option<(NodeId, String)> {
+        let steps = ChangeDir::steps(path);
+        match steps.split_last() {
+            Some((ChangeDir::Dir(name), parent)) => {
+                Some((self.follow(parent, missing)?, name.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    fn finish_output(&mut self) {
+        let Output::Listing(listing) = std::mem::replace(&mut self.output, Output::Ignored) else {
+            return;
+        };
+        if !listing.repeated {
+            return;
+        }
+        let removed = self
+            .tree
+            .children(listing.directory)
+            .map(|child| self.tree.get(child).get_name())
+            .filter(|name| !listing.names.contains(*name))
+            .map(str::to_string)
+            .collect::<Vec<String>>();
+        if !listing.added.is_empty() || !removed.is_empty() {
+            self.inconsistencies.push(Inconsistency::ChangedListing {
+                line: listing.line,
+                path: self.tree.path(listing.directory).to_string(),
+                added: listing.added,
+                removed,
+            });
+        }
+    }
+
+    /// Skips a `cd` or `ls` that can not be read, and its output
+    fn malformed(&mut self, line: usize, content: &str) {
+        self.finish_output();
+        let command = content.trim_start_matches('$').trim().to_string();
+        self.inconsistencies
+            .push(Inconsistency::MalformedCommand { line, command });
+    }
+
+    fn command(&mut self, line: usize, command: Command) {
+        self.finish_output();
+        match command {
+            Command::Ls(steps) => {
+                if let Some(directory) = self.follow(&steps, Missing::Report(line)) {
+                    self.output = Output::Listing(Listing {
+                        directory,
+                        line,
+                        repeated: !self.listed.insert(directory),
+                        names: HashSet::new(),
+                        added: vec![],
+                    });
+                }
+            }
+            Command::Cd(steps) => {
+                // there is no directory to go into when a file is in the way, so stay
+                if let Some(directory) = self.follow(&steps, Missing::Report(line)) {
+                    self.tree.current_working_directory = directory;
+                }
+            }
+            Command::Pwd => self.output = Output::WorkingDirectory,
+            Command::Other => {}
+            Command::Mkdir { parents, paths } => {
+                let missing = if parents {
+                    Missing::Create
+                } else {
+                    Missing::Fail
+                };
+                for path in paths {
+                    if let Some((parent, name)) = self.split(&path, missing) {
+                        if self.tree.find_child(parent, &name).is_none() {
+                            self.tree
+                                .add_child(parent, FileNode::Directory(Directory::new(name)));
+                        }
+                    }
+                }
+            }
+            Command::Rm { recursive, paths } => {
+                for path in paths {
+                    let Some((parent, name)) = self.split(&path, Missing::Fail) else {
+                        continue;
+                    };
+                    let Some(id) = self.tree.find_child(parent, &name) else {
+                        continue;
+                    };
+                    if self.tree.get(id).is_directory() && !recursive {
+                        continue;
+                    }
+                    let cwd = self.tree.current_working_directory;
+                    if cwd
+                        .ancestors(&self.tree.nodes)
+                        .any(|ancestor| ancestor == id)
+                    {
+                        self.tree.current_working_directory = parent;
+                    }
+                    self.tree.remove(id);
+                }
+            }
+            Command::Touch(paths) => {
+                for path in paths {
+                    if let Some((parent, name)) = self.split(&path, Missing::Fail) {
+                        if self.tree.find_child(parent, &name).is_none() {
+                            self.tree
+                                .add_child(parent, FileNode::File(File::new(name, 0)));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn output(&mut self, line: usize, content: &str) -> Result<(), Error> {
+        match &self.output {
+            Output::Ignored => {}
+            Output::WorkingDirectory => {
+                let found = content.trim();
+                let found = match found.trim_end_matches('/') {
+                    "" => found,
+                    trimmed => trimmed,
+                };
+                let expected = self.tree.path(self.tree.current_working_directory);
+                if found != expected {
+                    self.inconsistencies.push(Inconsistency::WrongDirectory {
+                        line,
+                        expected: expected.to_string(),
+                        found: found.to_string(),
+                    });
+                }
+                self.output = Output::Ignored;
+            }
+            Output::Listing(_) => {
+                if let Some(file_node) =
+                    listing_entry(content).map_err(|error| error.on_line(line))?
+                {
+                    self.list(line, file_node);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn list(&mut self, line: usize, file_node: FileNode) {
+        let Output::Listing(listing) = &mut self.output else {
+            return;
+        };
+        let directory = listing.directory;
+        let name = file_node.get_name().to_string();
+        if listing.repeated && self.tree.find_child(directory, &name).is_none() {
+            listing.added.push(name.clone());
+        }
+        listing.names.insert(name.clone());
+
+        let Some(existing) = self.tree.find_child(directory, &name) else {
+            self.tree.add_child(directory, file_node);
+            return;
+        };
+        let path = self.tree.path(existing).to_string();
+        match (self.tree.nodes[existing].get_mut(), file_node) {
+            (FileNode::File(before), FileNode::File(after)) if before.size != after.size => {
+                self.inconsistencies.push(Inconsistency::ChangedSize {
+                    line,
+                    path,
+                    before: before.size,
+                    after: after.size,
+                });
+                before.size = after.size;
+            }
+            (FileNode::File(_), FileNode::Directory(_))
+            | (FileNode::Directory(_), FileNode::File(_)) => {
+                self.inconsistencies
+                    .push(Inconsistency::ChangedKind { line, path });
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Rebuilds the file system from a transcript and reports where it contradicts itself. `ls`
+/// never removes anything: a directory that was listed with more entries before keeps them.
+/// `cd` or `ls` into a directory that was never listed creates it. `mkdir`, `rm` and `touch`
+/// change the tree like they change a disk and do nothing where the shell would fail, e.g.
+/// `rm` of a directory without `-r`. Other commands and their output are ignored, a `cd` or `ls`
+/// that can not be read is an [`Inconsistency::MalformedCommand`].
+///
+/// # Errors
+/// if a line of `ls` output can not be read, or, when `merge` is [`Merge::Strict`], with every
+/// inconsistency if there are any
+pub fn replay(input: &str, merge: Merge) -> Result<Replay, Error> {
+    let mut replayer = Replayer {
+        tree: Tree::new(Directory {
+            name: "/".to_string(),
+            path: "/".to_string(),
+        }),
+        merge,
+        listed: HashSet::new(),
+        output: Output::Ignored,
+        inconsistencies: vec![],
+    };
+
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        match Line::try_from(line) {
+            Ok(Line::CommandInput(command)) => replayer.command(line_number, command),
+            Ok(Line::CommandOutput(content)) => replayer.output(line_number, &content)?,
+            // only a `cd` or `ls` that can not be read fails to parse
+            Err(_) => replayer.malformed(line_number, line),
+        }
+    }
+    replayer.finish_output();
+    // a changed listing is only known once its output ends, after problems in the output
+    replayer.inconsistencies.sort_by_key(Inconsistency::line);
+
+    if replayer.merge == Merge::Strict && !replayer.inconsistencies.is_empty() {
+        return Err(Error::Inconsistent(replayer.inconsistencies));
+    }
+    Ok(Replay {
+        tree: replayer.tree,
+        inconsistencies: replayer.inconsistencies,
+    })
+}
+
+/// Every contradiction in the transcript, in order.
+///
+/// # Errors
+/// if a line of `ls` output can not be read
+pub fn validate(input: &str) -> Result<Vec<Inconsistency>, Error> {
+    replay(input, Merge::Lenient).map(|replay| replay.inconsistencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{replay, validate, Inconsistency, Merge};
+    use crate::day_7::{tests::EXAMPLE_INPUT, Error};
+
+    const CONTRADICTING: &str = "$ cd /
+$ ls
+dir a
+100 b.txt
+$ cd a
+$ ls
+10 c
+$ cd /
+$ ls
+dir a
+200 b.txt
+30 d
+$ cd x
+$ ls
+$ cd /
+$ ls
+5 a";
+
+    #[test]
+    fn consistent() {
+        assert_eq!(validate(EXAMPLE_INPUT).unwrap(), []);
+        let strict = replay(EXAMPLE_INPUT, Merge::Strict).unwrap();
+        assert!(strict.inconsistencies.is_empty());
+
+        // listing the same thing twice is fine
+        let twice =
+            format!("{EXAMPLE_INPUT}\n$ cd /\n$ ls\ndir a\n14848514 b.txt\n8504156 c.dat\ndir d");
+        assert_eq!(validate(&twice).unwrap(), []);
+    }
+
+    #[test]
+    fn inconsistencies() {
+        let inconsistencies = validate(CONTRADICTING).unwrap();
+        assert_eq!(
+            inconsistencies,
+            [
+                Inconsistency::ChangedListing {
+                    line: 9,
+                    path: "/".to_string(),
+                    added: vec!["d".to_string()],
+                    removed: vec![]
+                },
+                Inconsistency::ChangedSize {
+                    line: 11,
+                    path: "/b.txt".to_string(),
+                    before: 100,
+                    after: 200
+                },
+                Inconsistency::UnlistedDirectory {
+                    line: 13,
+                    path: "/x".to_string()
+                },
+                Inconsistency::ChangedListing {
+                    line: 16,
+                    path: "/".to_string(),
+                    added: vec![],
+                    removed: vec!["b.txt".to_string(), "d".to_string(), "x".to_string()]
+                },
+                Inconsistency::ChangedKind {
+                    line: 17,
+                    path: "/a".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            inconsistencies[0].to_string(),
+            "line 9: / was listed before, now with d"
+        );
+        assert_eq!(
+            inconsistencies[1].to_string(),
+            "line 11: /b.txt was 100 bytes, now 200"
+        );
+
+        let into_file = validate("$ cd /\n$ ls\n5 f\n$ cd f\n$ ls f/g").unwrap();
+        assert_eq!(
+            into_file,
+            [
+                Inconsistency::NotADirectory {
+                    line: 4,
+                    path: "/f".to_string()
+                },
+                Inconsistency::NotADirectory {
+                    line: 5,
+                    path: "/f".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            into_file[0].to_string(),
+            "line 4: /f is a file, not a directory"
+        );
+    }
+
+    #[test]
+    fn session() {
+        let session = r#"$ cd /
+$ mkdir -p "my docs/old"
+$ cd my\ docs
+$ pwd
+/my docs
+$ touch notes.txt
+$ ls -la
+total 8
+drwxr-xr-x 3 user user 4096 Dec  7 10:00 .
+drwxr-xr-x 3 user user 4096 Dec  7 10:00 ..
+-rw-r--r-- 1 user user    0 Dec  7 10:01 notes.txt
+drwxr-xr-x 2 user user 4096 Dec  7 10:00 old
+-rw-r--r-- 1 user user  300 2022-12-07 10:02 'two words.md'
+lrwxrwxrwx 1 user user    9 Dec  7 10:03 link -> notes.txt
+$ rm old
+rm: cannot remove 'old': Is a directory
+$ rm -r old notes.txt
+$ cd ..
+$ ls 'my docs'
+300 "two words.md"
+9 link
+$ pwd
+/"#;
+        let tree = replay(session, Merge::Strict).unwrap().tree;
+        assert_eq!(tree.get_size(tree.root()), 309);
+        assert_eq!(
+            tree.get_size(tree.find("/my docs/two words.md").unwrap()),
+            300
+        );
+        assert_eq!(tree.find("/my docs/old"), None);
+        assert_eq!(tree.find("/my docs/notes.txt"), None);
+
+        let wrong = validate("$ cd /\n$ mkdir a\n$ cd a\n$ pwd\n/b\n$ rm -r /a\n$ pwd\n/").unwrap();
+        assert_eq!(
+            wrong,
+            [Inconsistency::WrongDirectory {
+                line: 5,
+                expected: "/a".to_string(),
+                found: "/b".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn other_commands() {
+        let session = "$ cd /
+$ cat notes.txt
+12 apples
+$ vim
+$ ls
+5 a
+$ echo 'unclosed
+4 b";
+        let tree = replay(session, Merge::Strict).unwrap().tree;
+        assert_eq!(tree.get_size(tree.root()), 5);
+
+        let malformed = "$ cd /\n$ cd\n$ ls a b\n7 c\n$ cd 'a\n$ ls\n5 a";
+        let inconsistencies = validate(malformed).unwrap();
+        assert_eq!(
+            inconsistencies,
+            [
+                Inconsistency::MalformedCommand {
+                    line: 2,
+                    command: "cd".to_string()
+                },
+                Inconsistency::MalformedCommand {
+                    line: 3,
+                    command: "ls a b".to_string()
+                },
+                Inconsistency::MalformedCommand {
+                    line: 5,
+                    command: "cd 'a".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            inconsistencies[0].to_string(),
+            "line 2: can not follow \"cd\""
+        );
+        let tree = replay(malformed, Merge::Lenient).unwrap().tree;
+        assert_eq!(tree.get_size(tree.root()), 5);
+        assert!(matches!(
+            replay(malformed, Merge::Strict),
+            Err(Error::Inconsistent(found)) if found == inconsistencies
+        ));
+    }
+
+    #[test]
+    fn merging() {
+        let lenient = replay(CONTRADICTING, Merge::Lenient).unwrap();
+        let tree = &lenient.tree;
+        assert_eq!(tree.get_size(tree.find("/b.txt").unwrap()), 200);
+        assert!(tree.get(tree.find("/a").unwrap()).is_directory());
+        assert!(tree.get(tree.find("/x").unwrap()).is_directory());
+        assert_eq!(tree.get_size(tree.root()), 240);
+
+        match replay(CONTRADICTING, Merge::Strict) {
+            Err(Error::Inconsistent(inconsistencies)) => {
+                assert_eq!(inconsistencies, lenient.inconsistencies);
+            }
+            other => panic!("expected inconsistencies, got {other:?}"),
+        }
+    }
+}
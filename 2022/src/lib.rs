@@ -15,3 +15,5 @@ pub mod day_4;
 pub mod day_5;
 pub mod day_6;
 pub mod day_7;
+pub mod output;
+pub mod parsing;
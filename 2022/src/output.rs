@@ -0,0 +1,42 @@
+//! Rendering shared between puzzles.
+//!
+//! Reports render as plain text for people or as JSON for other tools. The JSON is written by
+//! hand, `json_string` escapes the strings in it.
+
+use std::fmt::Write;
+
+/// How a report is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+/// `s` as a JSON string literal
+pub(crate) fn json_string(s: &str) -> String {
+    let mut json = String::from('"');
+    for character in s.chars() {
+        match character {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            character if character.is_control() => {
+                let _ = write!(json, "\\u{:04x}", u32::from(character));
+            }
+            character => json.push(character),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::json_string;
+
+    #[test]
+    fn escaping() {
+        assert_eq!(json_string(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+        assert_eq!(json_string("tab\tnew\nline"), r#""tab\u0009new\nline""#);
+    }
+}
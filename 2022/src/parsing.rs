@@ -0,0 +1,312 @@
+//! Parsing helpers shared between puzzles.
+//!
+//! Many puzzle inputs are groups of lines separated by blank lines (inventories in day 1, the
+//! drawing and the instructions in day 5). [`groups`] walks such an input without copying it,
+//! [`read_groups`] reads it one group at a time.
+
+use std::{
+    fmt::Display,
+    io::{self, BufRead},
+    str::FromStr,
+};
+
+/// Lines of `input` between blank lines. `text` borrows from the input and does not include the
+/// line ending of the last line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Group<'a> {
+    /// 1-based line number of the first line in the group
+    pub line: usize,
+    pub text: &'a str,
+}
+
+/// A line that could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError<E> {
+    /// 1-based line number in the whole input
+    pub line: usize,
+    pub content: String,
+    pub reason: E,
+}
+
+impl<E: Display> Display for ParseError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {:?}: {}", self.line, self.content, self.reason)
+    }
+}
+
+impl<'a> Group<'a> {
+    /// Lines of the group with their 1-based line number. Line endings are removed.
+    pub fn lines(&self) -> impl Iterator<Item = (usize, &'a str)> {
+        let first = self.line;
+        self.text
+            .lines()
+            .enumerate()
+            .map(move |(index, line)| (first + index, line))
+    }
+
+    /// Parses every trimmed line of the group as a `T`.
+    ///
+    /// # Errors
+    /// the first line that can not be parsed
+    pub fn parse_lines<T: FromStr>(&self) -> Result<Vec<T>, ParseError<T::Err>> {
+        self.lines()
+            .map(|(line, content)| {
+                let content = content.trim();
+                content.parse::<T>().map_err(|reason| ParseError {
+                    line,
+                    content: content.to_string(),
+                    reason,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A [`Group`] that owns its text, see [`read_groups`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupBuf {
+    /// 1-based line number of the first line in the group
+    pub line: usize,
+    /// lines of the group joined with `\n`
+    pub text: String,
+}
+
+impl GroupBuf {
+    #[must_use]
+    pub fn as_group(&self) -> Group<'_> {
+        Group {
+            line: self.line,
+            text: &self.text,
+        }
+    }
+}
+
+/// Iterator over the groups of an input. See [`groups`].
+#[derive(Debug, Clone)]
+pub struct Groups<'a> {
+    input: &'a str,
+    offset: usize,
+    line: usize,
+}
+
+impl<'a> Groups<'a> {
+    /// Consumes one line and returns its line number, start and end without the line ending.
+    fn next_line(&mut self) -> Option<(usize, usize, usize)> {
+        if self.offset >= self.input.len() {
+            return None;
+        }
+        let start = self.offset;
+        let rest = &self.input[start..];
+        let (length, consumed) = match rest.find('\n') {
+            Some(newline) => (newline, newline + 1),
+            None => (rest.len(), rest.len()),
+        };
+        let end = if rest[..length].ends_with('\r') {
+            start + length - 1
+        } else {
+            start + length
+        };
+        self.offset += consumed;
+        self.line += 1;
+        Some((self.line, start, end))
+    }
+
+    /// What is left of the input after the groups returned so far
+    #[must_use]
+    pub fn remainder(&self) -> &'a str {
+        &self.input[self.offset..]
+    }
+}
+
+impl<'a> Iterator for Groups<'a> {
+    type Item = Group<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let input = self.input;
+        let is_blank = |start: usize, end: usize| input[start..end].trim().is_empty();
+
+        // skip blank lines before the group
+        let (line, start, mut end) = loop {
+            let (line, start, end) = self.next_line()?;
+            if !is_blank(start, end) {
+                break (line, start, end);
+            }
+        };
+        // take lines until the next blank line or the end of the input
+        while let Some((_, next_start, next_end)) = self.next_line() {
+            if is_blank(next_start, next_end) {
+                break;
+            }
+            end = next_end;
+        }
+
+        Some(Group {
+            line,
+            text: &input[start..end],
+        })
+    }
+}
+
+/// Splits `input` on blank lines. Lines may end with `\n` or `\r\n`, lines holding only white
+/// space count as blank and any number of blank lines may separate, precede or follow the groups.
+#[must_use]
+pub fn groups(input: &str) -> Groups<'_> {
+    Groups {
+        input,
+        offset: 0,
+        line: 0,
+    }
+}
+
+/// Iterator over the groups of an input that is read line by line. See [`read_groups`].
+#[derive(Debug)]
+pub struct ReadGroups<R> {
+    lines: io::Lines<R>,
+    line: usize,
+}
+
+impl<R: BufRead> Iterator for ReadGroups<R> {
+    type Item = io::Result<GroupBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut group: Option<GroupBuf> = None;
+        for content in self.lines.by_ref() {
+            self.line += 1;
+            let content = match content {
+                Ok(content) => content,
+                Err(error) => return Some(Err(error)),
+            };
+            if content.trim().is_empty() {
+                if group.is_some() {
+                    break;
+                }
+                continue;
+            }
+            match &mut group {
+                Some(group) => {
+                    group.text.push('\n');
+                    group.text.push_str(&content);
+                }
+                None => {
+                    group = Some(GroupBuf {
+                        line: self.line,
+                        text: content,
+                    });
+                }
+            }
+        }
+        group.map(Ok)
+    }
+}
+
+/// Splits the input read from `reader` on blank lines like [`groups`] does. Only the group being
+/// read is kept in memory.
+pub fn read_groups<R: BufRead>(reader: R) -> ReadGroups<R> {
+    ReadGroups {
+        lines: reader.lines(),
+        line: 0,
+    }
+}
+
+/// Parses every line of every group in `input` as a `T`.
+///
+/// # Errors
+/// the first line that can not be parsed
+pub fn parse_groups<T: FromStr>(input: &str) -> Result<Vec<Vec<T>>, ParseError<T::Err>> {
+    groups(input).map(|group| group.parse_lines()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{groups, parse_groups, read_groups, Group, GroupBuf};
+
+    #[test]
+    fn test_groups() {
+        let input = "\n\na\nb\n\n\nc\n  \nd\ne\n\n";
+        let output = groups(input).collect::<Vec<Group>>();
+        assert_eq!(
+            output,
+            vec![
+                Group {
+                    line: 3,
+                    text: "a\nb"
+                },
+                Group { line: 7, text: "c" },
+                Group {
+                    line: 9,
+                    text: "d\ne"
+                },
+            ]
+        );
+        assert_eq!(groups("").count(), 0);
+        assert_eq!(groups("\n \n").count(), 0);
+        assert_eq!(groups("a").next().unwrap().text, "a");
+    }
+
+    #[test]
+    fn windows_line_endings() {
+        let input = "a\r\nb\r\n\r\nc\r\n";
+        let output = groups(input).collect::<Vec<Group>>();
+        assert_eq!(output[0].text, "a\r\nb");
+        assert_eq!(output[1], Group { line: 4, text: "c" });
+        assert_eq!(
+            output[0].lines().collect::<Vec<(usize, &str)>>(),
+            vec![(1, "a"), (2, "b")]
+        );
+    }
+
+    #[test]
+    fn reading() {
+        let read = |input: &str| {
+            read_groups(input.as_bytes())
+                .collect::<Result<Vec<GroupBuf>, _>>()
+                .unwrap()
+        };
+        let input = "\n\na\nb\n\n\nc\n  \nd\ne\n\n";
+        assert_eq!(
+            read(input)
+                .iter()
+                .map(GroupBuf::as_group)
+                .collect::<Vec<Group>>(),
+            groups(input).collect::<Vec<Group>>()
+        );
+        assert_eq!(
+            read("a\r\nb\r\n\r\nc\r\n"),
+            vec![
+                GroupBuf {
+                    line: 1,
+                    text: "a\nb".to_string()
+                },
+                GroupBuf {
+                    line: 4,
+                    text: "c".to_string()
+                },
+            ]
+        );
+        assert!(read("\n \n").is_empty());
+        assert!(read_groups(&[b'1', 0xff][..]).next().unwrap().is_err());
+    }
+
+    #[test]
+    fn remainder() {
+        let mut sections = groups("head\n\nbody\nmore");
+        assert_eq!(sections.next().unwrap().text, "head");
+        assert_eq!(sections.remainder(), "body\nmore");
+    }
+
+    #[test]
+    fn typed_lines() {
+        assert_eq!(
+            parse_groups::<u32>(" 1\n2 \n\n3").unwrap(),
+            vec![vec![1, 2], vec![3]]
+        );
+
+        let error = parse_groups::<u32>("1\n\n2\nx\n").unwrap_err();
+        assert_eq!(error.line, 4);
+        assert_eq!(error.content, "x");
+        assert_eq!(
+            error.to_string(),
+            "line 4: \"x\": invalid digit found in string"
+        );
+    }
+}